@@ -0,0 +1,20 @@
+// Writes the summary report of the simulation to {{output_dir}}/report.xml for `cfdsim check`
+package macro;
+
+import star.common.*;
+
+public class report extends StarMacro {
+
+  public void execute() {
+    Simulation simulation = getActiveSimulation();
+    String path = resolvePath("{{output_dir}}/report.xml");
+    simulation.println("cfdsim: writing the summary report of {{case}} to " + path);
+    simulation.saveSummaryReport(path);
+    simulation.println("@@cfdsim {\"files\": [\"" + json(path) + "\"]}");
+  }
+
+  // escapes a string for a JSON string literal
+  private static String json(String text) {
+    return text.replace("\\", "\\\\").replace("\"", "\\\"");
+  }
+}
@@ -0,0 +1,26 @@
+// Writes the hardcopies of the RI_tel, RI_wind, vort_tel and vort_wind scenes to {{output_dir}} for `cfdsim check`
+package macro;
+
+import star.common.*;
+import star.vis.*;
+
+public class scenes_views extends StarMacro {
+
+  private static final String[] SCENES = {"RI_tel", "RI_wind", "vort_tel", "vort_wind"};
+
+  public void execute() {
+    Simulation simulation = getActiveSimulation();
+    for (String name : SCENES) {
+      Scene scene = simulation.getSceneManager().getScene(name);
+      String path = resolvePath("{{output_dir}}/" + name + ".png");
+      simulation.println("cfdsim: writing the " + name + " scene of {{case}} to " + path);
+      scene.printAndWait(path, 1, 1920, 1080, true, false);
+      simulation.println("@@cfdsim {\"files\": [\"" + json(path) + "\"]}");
+    }
+  }
+
+  // escapes a string for a JSON string literal
+  private static String json(String text) {
+    return text.replace("\\", "\\\\").replace("\"", "\\\"");
+  }
+}
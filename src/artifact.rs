@@ -0,0 +1,238 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Variables;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArtifactError {
+    #[error("invalid artifact pattern {0:?}")]
+    Pattern(String, #[source] glob::PatternError),
+    #[error("the artifact {0:?} is missing")]
+    Missing(PathBuf),
+    #[error("failed to move the artifact {0:?} to {1:?}")]
+    Move(PathBuf, PathBuf, #[source] io::Error),
+}
+type Result<T> = std::result::Result<T, ArtifactError>;
+
+/// File produced by a macro, moved into the case destination directory after the run
+///
+/// `from` is a path or a glob pattern, relative to the working directory of the run if it is not absolute.
+/// A path is renamed `to`, `<case>.<extension of from>` by default,
+/// and the files matching a pattern keep their names in the `to` sub-directory,
+/// or are renamed `<case>_<file name>` if `to` is not given
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Artifact {
+    pub from: PathBuf,
+    pub to: Option<String>,
+}
+impl Artifact {
+    pub fn new(from: impl Into<PathBuf>, to: Option<String>) -> Self {
+        Self {
+            from: from.into(),
+            to,
+        }
+    }
+    /// Replaces the placeholders of `from` and `to` with their values
+    ///
+    /// Returns the first placeholder without a value as error
+    pub fn render(&self, variables: &Variables) -> std::result::Result<Self, String> {
+        Ok(Self {
+            from: variables.render(&self.from.to_string_lossy())?.into(),
+            to: self
+                .to
+                .as_deref()
+                .map(|to| variables.render(to))
+                .transpose()?,
+        })
+    }
+    /// Checks if `from` is a glob pattern
+    pub fn is_pattern(&self) -> bool {
+        self.from.to_string_lossy().contains(['*', '?', '['])
+    }
+    /// Path to the artifact of a path in the `destination` directory, `None` for a pattern
+    pub fn target(&self, case: &str, destination: &Path) -> Option<PathBuf> {
+        if self.is_pattern() {
+            return None;
+        }
+        Some(destination.join(match self.to.as_deref() {
+            Some(to) => to.to_string(),
+            None => match self.from.extension() {
+                Some(extension) => format!("{case}.{}", extension.to_string_lossy()),
+                None => case.to_string(),
+            },
+        }))
+    }
+    /// Source and destination of the files of the artifact written in `work_dir`
+    ///
+    /// Fails if the path does not exist or if the pattern does not match any file
+    pub fn files(
+        &self,
+        case: &str,
+        work_dir: &Path,
+        destination: &Path,
+    ) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let from = work_dir.join(&self.from);
+        if let Some(to) = self.target(case, destination) {
+            return if from.is_file() {
+                Ok(vec![(from, to)])
+            } else {
+                Err(ArtifactError::Missing(self.from.clone()))
+            };
+        }
+        let pattern = from.to_string_lossy();
+        let files: Vec<_> = glob::glob(&pattern)
+            .map_err(|e| ArtifactError::Pattern(pattern.to_string(), e))?
+            .filter_map(|path| path.ok())
+            .filter(|path| path.is_file())
+            .collect();
+        if files.is_empty() {
+            return Err(ArtifactError::Missing(self.from.clone()));
+        }
+        Ok(files
+            .into_iter()
+            .map(|file| {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let to = match self.to.as_deref() {
+                    Some(to) => destination.join(to).join(name.as_ref()),
+                    None => destination.join(format!("{case}_{name}")),
+                };
+                (file, to)
+            })
+            .collect())
+    }
+}
+
+/// Moves the artifacts of a case from `work_dir` into `destination`, returning their new paths
+///
+/// All the artifacts are checked before any is moved
+pub fn collect_artifacts(
+    artifacts: &[Artifact],
+    case: &str,
+    work_dir: &Path,
+    destination: &Path,
+) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for artifact in artifacts {
+        files.extend(artifact.files(case, work_dir, destination)?);
+    }
+    files
+        .into_iter()
+        .map(|(from, to)| {
+            fs::create_dir_all(to.parent().unwrap_or(destination))
+                .and_then(|_| move_file(&from, &to))
+                .map_err(|e| ArtifactError::Move(from, to.clone(), e))?;
+            Ok(to)
+        })
+        .collect()
+}
+
+/// Moves a file, copying it if `from` and `to` are on different file systems
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to).or_else(|_| {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const CASE: &str = "zen30az000_OS_7ms";
+
+    /// Working directory with the files `names`
+    fn work_dir(test: &str, names: &[&str]) -> TempDir {
+        let dir = TempDir::new(test);
+        for name in names {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn target() {
+        let destination = Path::new("/dest");
+        assert_eq!(
+            Artifact::new("sim_force_update.sim", None).target(CASE, destination),
+            Some(PathBuf::from("/dest/zen30az000_OS_7ms.sim"))
+        );
+        assert_eq!(
+            Artifact::new("/tmp/out", None).target(CASE, destination),
+            Some(PathBuf::from("/dest/zen30az000_OS_7ms"))
+        );
+        assert_eq!(
+            Artifact::new("report.xml", Some("reports/r.xml".to_string()))
+                .target(CASE, destination),
+            Some(PathBuf::from("/dest/reports/r.xml"))
+        );
+        assert_eq!(Artifact::new("*.png", None).target(CASE, destination), None);
+    }
+
+    #[test]
+    fn files_of_a_path() {
+        let dir = work_dir("artifact_path", &["sim_force_update.sim"]);
+        let destination = Path::new("/dest");
+        assert_eq!(
+            Artifact::new("sim_force_update.sim", None)
+                .files(CASE, &dir, destination)
+                .unwrap(),
+            [(
+                dir.join("sim_force_update.sim"),
+                PathBuf::from("/dest/zen30az000_OS_7ms.sim")
+            )]
+        );
+        assert!(matches!(
+            Artifact::new("missing.sim", None).files(CASE, &dir, destination),
+            Err(ArtifactError::Missing(_))
+        ));
+    }
+
+    #[test]
+    fn files_of_a_pattern() {
+        let dir = work_dir("artifact_pattern", &["a.png", "b.png", "c.txt"]);
+        let destination = Path::new("/dest");
+        let mut files = Artifact::new("*.png", None)
+            .files(CASE, &dir, destination)
+            .unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                (dir.join("a.png"), destination.join(format!("{CASE}_a.png"))),
+                (dir.join("b.png"), destination.join(format!("{CASE}_b.png"))),
+            ]
+        );
+        let files = Artifact::new("*.txt", Some("texts".to_string()))
+            .files(CASE, &dir, destination)
+            .unwrap();
+        assert_eq!(
+            files,
+            [(dir.join("c.txt"), destination.join("texts/c.txt"))]
+        );
+        assert!(matches!(
+            Artifact::new("*.sim", None).files(CASE, &dir, destination),
+            Err(ArtifactError::Missing(_))
+        ));
+    }
+
+    #[test]
+    fn collect_checks_all_the_artifacts_first() {
+        let dir = work_dir("artifact_collect", &["a.png"]);
+        let destination = dir.join("dest");
+        let artifacts = [
+            Artifact::new("a.png", None),
+            Artifact::new("missing.sim", None),
+        ];
+        assert!(collect_artifacts(&artifacts, CASE, &dir, &destination).is_err());
+        assert!(dir.join("a.png").is_file());
+        let moved = collect_artifacts(&artifacts[..1], CASE, &dir, &destination).unwrap();
+        assert_eq!(moved, [destination.join(format!("{CASE}.png"))]);
+        assert!(moved[0].is_file() && !dir.join("a.png").exists());
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    fmt::{self, Display},
+    fs,
+    hash::Hasher,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{cache::Fnv, cache_dir};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundledError {
+    #[error("failed to read the java macro {0:?}")]
+    Read(PathBuf, #[source] io::Error),
+    #[error("failed to write the java macro {0:?}")]
+    Write(PathBuf, #[source] io::Error),
+    #[error("{0:?} has been customized, use --force to overwrite it")]
+    Customized(PathBuf),
+}
+type Result<T> = std::result::Result<T, BundledError>;
+
+/// Java macro shipped with the CLI
+#[derive(Debug, Clone, Copy)]
+pub struct BundledMacro {
+    /// File name of the macro
+    pub name: &'static str,
+    source: &'static str,
+}
+
+/// Java macros used by the `check` command
+pub const BUNDLED_MACROS: [BundledMacro; 2] = [BundledMacro::REPORT, BundledMacro::SCENES_VIEWS];
+
+/// Status of the copy of a bundled macro in the macros directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroStatus {
+    /// there is no copy, the bundled macro is used
+    Bundled,
+    /// unmodified copy of the bundled macro
+    Exported,
+    /// unmodified copy of the macro bundled with another version of the CLI, the bundled macro is used
+    Outdated(String),
+    /// modified copy, used instead of the bundled macro
+    Customized,
+}
+impl Display for MacroStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroStatus::Bundled => write!(f, "bundled"),
+            MacroStatus::Exported => write!(f, "exported"),
+            MacroStatus::Outdated(version) => write!(f, "outdated (exported by cfdsim {version})"),
+            MacroStatus::Customized => write!(f, "customized"),
+        }
+    }
+}
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write(text.as_bytes());
+    hasher.finish()
+}
+
+impl BundledMacro {
+    pub const REPORT: Self = Self {
+        name: "report.java",
+        source: include_str!("../macros/report.java"),
+    };
+    pub const SCENES_VIEWS: Self = Self {
+        name: "scenes_views.java",
+        source: include_str!("../macros/scenes_views.java"),
+    };
+    /// Source of the macro
+    pub fn source(&self) -> &'static str {
+        self.source
+    }
+    /// Source of the macro with a first line stamping the version of the CLI and the hash of the source,
+    /// the hash telling apart the copies that have been modified
+    pub fn stamped(&self) -> String {
+        format!(
+            "// cfdsim {} {} {:016x}\n{}",
+            env!("CARGO_PKG_VERSION"),
+            self.name,
+            hash(self.source),
+            self.source
+        )
+    }
+    /// Status of the copy of the macro in `dir`
+    pub fn status(&self, dir: &Path) -> Result<MacroStatus> {
+        let path = dir.join(self.name);
+        if !path.is_file() {
+            return Ok(MacroStatus::Bundled);
+        }
+        let text = fs::read_to_string(&path).map_err(|e| BundledError::Read(path, e))?;
+        let Some((stamp, source)) = text.split_once('\n') else {
+            return Ok(MacroStatus::Customized);
+        };
+        let stamp: Vec<_> = stamp.split_whitespace().collect();
+        let [_, "cfdsim", version, name, stamped_hash] = stamp[..] else {
+            return Ok(MacroStatus::Customized);
+        };
+        if name != self.name || stamped_hash != format!("{:016x}", hash(source)) {
+            return Ok(MacroStatus::Customized);
+        }
+        Ok(if source == self.source {
+            MacroStatus::Exported
+        } else {
+            MacroStatus::Outdated(version.to_string())
+        })
+    }
+    /// Writes the stamped macro into `dir`
+    ///
+    /// A customized copy is overwritten only if `force` is set
+    pub fn export(&self, dir: &Path, force: bool) -> Result<PathBuf> {
+        let path = dir.join(self.name);
+        if !force && self.status(dir)? == MacroStatus::Customized {
+            return Err(BundledError::Customized(path));
+        }
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&path, self.stamped()))
+            .map_err(|e| BundledError::Write(path.clone(), e))?;
+        Ok(path)
+    }
+    /// Path to the macro to run
+    ///
+    /// This is the customized copy in `dir` if there is one,
+    /// otherwise the bundled macro written to `<cache dir>/macros/<version>/`
+    pub fn path(&self, dir: &Path) -> Result<PathBuf> {
+        if self.status(dir)? == MacroStatus::Customized {
+            return Ok(dir.join(self.name));
+        }
+        let bundled = cache_dir()
+            .join("macros")
+            .join(env!("CARGO_PKG_VERSION"))
+            .join(self.name);
+        if fs::read_to_string(&bundled).ok().as_deref() != Some(self.source) {
+            fs::create_dir_all(bundled.parent().unwrap())
+                .and_then(|_| fs::write(&bundled, self.source))
+                .map_err(|e| BundledError::Write(bundled.clone(), e))?;
+        }
+        Ok(bundled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn status() {
+        let dir = TempDir::new("bundled_status");
+        let report = BundledMacro::REPORT;
+        let path = dir.join(report.name);
+        assert_eq!(report.status(&dir).unwrap(), MacroStatus::Bundled);
+
+        assert_eq!(report.export(&dir, false).unwrap(), path);
+        assert_eq!(report.status(&dir).unwrap(), MacroStatus::Exported);
+        assert_eq!(
+            report
+                .path(&dir)
+                .unwrap()
+                .parent()
+                .unwrap()
+                .file_name()
+                .unwrap(),
+            env!("CARGO_PKG_VERSION")
+        );
+
+        let old = "// report macro of an older version\n";
+        fs::write(
+            &path,
+            format!("// cfdsim 0.1.0 report.java {:016x}\n{old}", hash(old)),
+        )
+        .unwrap();
+        assert_eq!(
+            report.status(&dir).unwrap(),
+            MacroStatus::Outdated("0.1.0".to_string())
+        );
+        assert_ne!(report.path(&dir).unwrap(), path);
+
+        fs::write(&path, report.stamped().replace("report.xml", "summary.xml")).unwrap();
+        assert_eq!(report.status(&dir).unwrap(), MacroStatus::Customized);
+        assert_eq!(report.path(&dir).unwrap(), path);
+        assert!(matches!(
+            report.export(&dir, false),
+            Err(BundledError::Customized(_))
+        ));
+        // a copy without the stamp line is customized too
+        fs::write(&path, report.source()).unwrap();
+        assert_eq!(report.status(&dir).unwrap(), MacroStatus::Customized);
+
+        report.export(&dir, true).unwrap();
+        assert_eq!(report.status(&dir).unwrap(), MacroStatus::Exported);
+    }
+}
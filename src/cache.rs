@@ -0,0 +1,357 @@
+use std::{
+    env,
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{config, home_dir, timestamp::timestamp};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("report cache I/O failed")]
+    Io(#[from] io::Error),
+    #[error("invalid report cache entry: {0:?}")]
+    Entry(PathBuf),
+}
+type Result<T> = std::result::Result<T, CacheError>;
+
+/// FNV-1a 64 bits hasher
+///
+/// Unlike the standard library hasher, the hash values are stable across Rust releases
+pub struct Fnv(u64);
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Hash of the content of a file
+pub fn content_hash(path: &Path) -> io::Result<u64> {
+    let mut reader = BufReader::with_capacity(1 << 20, File::open(path)?);
+    let mut hasher = Fnv::default();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break Ok(hasher.finish());
+        }
+        hasher.write(&buf[..n]);
+    }
+}
+
+/// Identity of a sim file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimIdentity {
+    pub sim: PathBuf,
+    pub size: u64,
+    /// modification time in nanoseconds since the UNIX epoch
+    pub mtime: u128,
+    pub hash: Option<u64>,
+}
+impl SimIdentity {
+    /// Identity of the sim file at `path`, including the content hash if `hash` is true
+    pub fn new(path: &Path, hash: bool) -> io::Result<Self> {
+        let sim = path.canonicalize()?;
+        let metadata = fs::metadata(&sim)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        Ok(Self {
+            size: metadata.len(),
+            mtime,
+            hash: if hash {
+                Some(content_hash(&sim)?)
+            } else {
+                None
+            },
+            sim,
+        })
+    }
+    /// Checks if `other`, the current identity of the sim, is the same than `self`
+    ///
+    /// The content hashes are compared only if `other` has one
+    fn matches(&self, other: &SimIdentity) -> bool {
+        self.sim == other.sim
+            && self.size == other.size
+            && self.mtime == other.mtime
+            && (other.hash.is_none() || self.hash == other.hash)
+    }
+    fn key(&self) -> String {
+        let mut hasher = Fnv::default();
+        hasher.write(self.sim.as_os_str().as_encoded_bytes());
+        format!("{:016x}", hasher.finish())
+    }
+    fn parse(meta: &str) -> Option<Self> {
+        let mut sim = None;
+        let mut size = None;
+        let mut mtime = None;
+        let mut hash = None;
+        for line in meta.lines() {
+            match line.split_once('=')? {
+                ("sim", value) => sim = Some(PathBuf::from(value)),
+                ("size", value) => size = value.parse().ok(),
+                ("mtime", value) => mtime = value.parse().ok(),
+                ("hash", value) => hash = u64::from_str_radix(value, 16).ok(),
+                _ => (),
+            }
+        }
+        Some(Self {
+            sim: sim?,
+            size: size?,
+            mtime: mtime?,
+            hash,
+        })
+    }
+    fn to_meta(&self) -> String {
+        let mut meta = format!(
+            "sim={}\nsize={}\nmtime={}\n",
+            self.sim.display(),
+            self.size,
+            self.mtime
+        );
+        if let Some(hash) = self.hash {
+            meta.push_str(&format!("hash={hash:016x}\n"));
+        }
+        meta
+    }
+}
+
+/// Report cache entry
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub identity: SimIdentity,
+    pub report: PathBuf,
+}
+impl CacheEntry {
+    /// Checks if the sim has not changed since the report was generated
+    pub fn is_fresh(&self) -> bool {
+        SimIdentity::new(&self.identity.sim, false)
+            .is_ok_and(|current| self.identity.matches(&current))
+    }
+    /// Time the report was cached
+    pub fn cached(&self) -> Option<String> {
+        fs::metadata(&self.report)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(timestamp)
+    }
+    /// Modification time of the sim when the report was generated
+    pub fn modified(&self) -> String {
+        let nanos = self.identity.mtime;
+        timestamp(
+            UNIX_EPOCH
+                + Duration::new(
+                    (nanos / 1_000_000_000) as u64,
+                    (nanos % 1_000_000_000) as u32,
+                ),
+        )
+    }
+}
+
+/// Cache directory of the CLI: `$XDG_CACHE_HOME/cfdsim` or `~/.cache/cfdsim`
+pub fn cache_dir() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".cache"))
+        .join("cfdsim")
+}
+
+/// Cache of the StarCCM+ summary reports
+///
+/// The default cache directory is `cache.dir` of the configuration
+#[derive(Debug, Clone)]
+pub struct ReportCache {
+    root: PathBuf,
+}
+impl Default for ReportCache {
+    fn default() -> Self {
+        Self::new(&config().cache.dir)
+    }
+}
+impl ReportCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    /// Returns the path to the cached report of the sim file, if the sim has not changed
+    ///
+    /// If `hash` is true, the content of the sim must match the content hash of the cached entry
+    pub fn get(&self, sim: &Path, hash: bool) -> Result<Option<PathBuf>> {
+        let identity = SimIdentity::new(sim, hash)?;
+        let key = identity.key();
+        let Ok(meta) = fs::read_to_string(self.root.join(&key).with_extension("meta")) else {
+            return Ok(None);
+        };
+        let report = self.root.join(key).with_extension("xml");
+        Ok(SimIdentity::parse(&meta)
+            .filter(|cached| cached.matches(&identity) && report.is_file())
+            .map(|_| report))
+    }
+    /// Copies the `report` of the sim file into the cache and returns the path to the cached report
+    pub fn insert(&self, sim: &Path, report: &Path, hash: bool) -> Result<PathBuf> {
+        let identity = SimIdentity::new(sim, hash)?;
+        let key = identity.key();
+        fs::create_dir_all(&self.root)?;
+        let cached = self.root.join(&key).with_extension("xml");
+        fs::copy(report, &cached)?;
+        fs::write(
+            self.root.join(key).with_extension("meta"),
+            identity.to_meta(),
+        )?;
+        Ok(cached)
+    }
+    /// Lists the cache entries
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = vec![];
+        let dir = match fs::read_dir(&self.root) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in dir {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "meta") {
+                continue;
+            }
+            let identity = SimIdentity::parse(&fs::read_to_string(&path)?)
+                .ok_or_else(|| CacheError::Entry(path.clone()))?;
+            entries.push(CacheEntry {
+                identity,
+                report: path.with_extension("xml"),
+            });
+        }
+        entries.sort_by(|a, b| a.identity.sim.cmp(&b.identity.sim));
+        Ok(entries)
+    }
+    /// Removes the entries of the sims that have changed or no longer exist, or all the entries if `all` is true
+    ///
+    /// Returns the removed entries
+    pub fn prune(&self, all: bool) -> Result<Vec<CacheEntry>> {
+        let mut removed = vec![];
+        for entry in self.entries()? {
+            if all || !entry.is_fresh() {
+                fs::remove_file(entry.report.with_extension("meta"))?;
+                if let Err(e) = fs::remove_file(&entry.report)
+                    && e.kind() != io::ErrorKind::NotFound
+                {
+                    return Err(e.into());
+                }
+                removed.push(entry);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn identity_round_trip() {
+        let identity = SimIdentity {
+            sim: PathBuf::from("/data/zen30az000_OS_7ms.sim"),
+            size: 1024,
+            mtime: 1_700_000_000_123_456_789,
+            hash: Some(0x0123456789abcdef),
+        };
+        assert_eq!(
+            SimIdentity::parse(&identity.to_meta()),
+            Some(identity.clone())
+        );
+        let unhashed = SimIdentity {
+            hash: None,
+            ..identity.clone()
+        };
+        assert_eq!(
+            SimIdentity::parse(&unhashed.to_meta()),
+            Some(unhashed.clone())
+        );
+        assert_eq!(SimIdentity::parse("sim=/data/a.sim\nsize=1\n"), None);
+        assert_eq!(SimIdentity::parse("not a meta file"), None);
+
+        // the content hashes are only compared if the current identity has one
+        assert!(identity.matches(&unhashed));
+        assert!(!unhashed.matches(&identity));
+        assert!(!identity.matches(&SimIdentity {
+            hash: Some(0),
+            ..identity.clone()
+        }));
+        assert!(!identity.matches(&SimIdentity {
+            size: 2048,
+            ..unhashed
+        }));
+    }
+
+    #[test]
+    fn get_the_inserted_reports() {
+        let dir = TempDir::new("cache_get");
+        let cache = ReportCache::new(dir.join("reports"));
+        let sim = dir.join("zen30az000_OS_7ms.sim");
+        fs::write(&sim, "sim").unwrap();
+        let report = dir.join("report.xml");
+        fs::write(&report, "<SummaryReport/>").unwrap();
+        assert_eq!(cache.get(&sim, false).unwrap(), None);
+
+        let cached = cache.insert(&sim, &report, true).unwrap();
+        assert_eq!(fs::read_to_string(&cached).unwrap(), "<SummaryReport/>");
+        assert_eq!(cache.get(&sim, false).unwrap(), Some(cached.clone()));
+        assert_eq!(cache.get(&sim, true).unwrap(), Some(cached));
+
+        fs::write(&sim, "updated sim").unwrap();
+        assert_eq!(cache.get(&sim, false).unwrap(), None);
+    }
+
+    #[test]
+    fn prune_the_changed_sims() {
+        let dir = TempDir::new("cache_prune");
+        let cache = ReportCache::new(dir.join("reports"));
+        let report = dir.join("report.xml");
+        fs::write(&report, "<SummaryReport/>").unwrap();
+        let sims: Vec<_> = [
+            "zen30az000_OS_7ms",
+            "zen30az045_OS_7ms",
+            "zen30az090_OS_7ms",
+        ]
+        .iter()
+        .map(|case| {
+            let sim = dir.join(format!("{case}.sim"));
+            fs::write(&sim, "sim").unwrap();
+            cache.insert(&sim, &report, false).unwrap();
+            sim.canonicalize().unwrap()
+        })
+        .collect();
+        fs::write(&sims[0], "updated sim").unwrap();
+        fs::remove_file(&sims[1]).unwrap();
+
+        let removed = cache.prune(false).unwrap();
+        let removed: Vec<_> = removed.iter().map(|entry| &entry.identity.sim).collect();
+        assert_eq!(removed, [&sims[0], &sims[1]]);
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].identity.sim, sims[2]);
+        assert!(entries[0].is_fresh());
+
+        assert_eq!(cache.prune(true).unwrap().len(), 1);
+        assert!(cache.entries().unwrap().is_empty());
+        assert_eq!(fs::read_dir(cache.root()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Artifact, CaseFolder, Variables, Verdict};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CampaignError {
+    #[error("failed to read the campaign {0:?}")]
+    Read(PathBuf, #[source] io::Error),
+    #[error("failed to parse the campaign {0:?}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("the campaign has no case")]
+    Empty,
+    #[error("invalid exclusion pattern {0:?}")]
+    Exclude(String, #[source] glob::PatternError),
+    #[error("the campaign has no {0}")]
+    Missing(&'static str),
+    #[error("failed to list the sims of {0:?}")]
+    Source(PathBuf, #[source] io::Error),
+    #[error("{{{{{placeholder}}}}} in {text:?} has no value for {case}")]
+    Placeholder {
+        text: String,
+        placeholder: String,
+        case: String,
+    },
+}
+type Result<T> = std::result::Result<T, CampaignError>;
+
+/// Cases of a campaign: all the combinations of the zenith angles, azimuth angles, configurations and wind speeds
+/// but the excluded ones
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CaseMatrix {
+    pub zen: Vec<u32>,
+    pub az: Vec<u32>,
+    /// Configurations, e.g. `OS`, or configurations with their wind speed, e.g. `OS_7ms`, if no wind speed is given
+    pub config: Vec<String>,
+    /// Wind speeds in m/s
+    #[serde(default)]
+    pub wind_speed: Vec<u32>,
+    /// Glob patterns of the excluded case names, e.g. `*_OS_17ms`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+impl CaseMatrix {
+    /// Case names `zen<zen>az<az>_<config>_<wind speed>ms`, by configuration, wind speed, azimuth and zenith
+    pub fn cases(&self) -> Vec<String> {
+        let configs: Vec<_> = if self.wind_speed.is_empty() {
+            self.config.clone()
+        } else {
+            self.config
+                .iter()
+                .flat_map(|config| {
+                    self.wind_speed
+                        .iter()
+                        .map(move |wind_speed| format!("{config}_{wind_speed}ms"))
+                })
+                .collect()
+        };
+        let exclude: Vec<_> = self
+            .exclude
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect();
+        let mut cases = vec![];
+        for config in &configs {
+            for az in &self.az {
+                for zen in &self.zen {
+                    let case = format!("zen{zen}az{az:03}_{config}");
+                    if !exclude.iter().any(|pattern| pattern.matches(&case)) {
+                        cases.push(case);
+                    }
+                }
+            }
+        }
+        cases
+    }
+}
+
+/// Files marking the progress of a case, they may use the placeholders of the macro templates
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Progress {
+    /// File written once the case is solved, e.g. `/home/ubuntu/mnt/sims_solved/{{case}}.sim`
+    pub solved: Option<String>,
+    /// File written once the case is post-processed
+    pub post_processed: Option<String>,
+}
+
+/// Joins a relative progress marker to `dir`,
+/// the markers starting with `{{output_dir}}` or `{{work_dir}}` being absolute
+fn join_marker(dir: &Path, marker: String) -> String {
+    let placed = marker.strip_prefix("{{").is_some_and(|rest| {
+        let rest = rest.trim_start();
+        rest.starts_with("output_dir") || rest.starts_with("work_dir")
+    });
+    if placed || Path::new(&marker).is_absolute() {
+        marker
+    } else {
+        dir.join(marker).to_string_lossy().into_owned()
+    }
+}
+
+/// Status of a case of a campaign, from the least to the most advanced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CaseStatus {
+    /// the sim is not in the source directory
+    Missing,
+    /// there is no checklist folder
+    NotChecked,
+    Fail,
+    Pass,
+    Solved,
+    PostProcessed,
+}
+impl Display for CaseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaseStatus::Missing => write!(f, "MISSING"),
+            CaseStatus::NotChecked => write!(f, "NOT CHECKED"),
+            CaseStatus::Fail => write!(f, "FAIL"),
+            CaseStatus::Pass => write!(f, "PASS"),
+            CaseStatus::Solved => write!(f, "SOLVED"),
+            CaseStatus::PostProcessed => write!(f, "POST-PROCESSED"),
+        }
+    }
+}
+
+/// Manifest of the cases of a campaign and of the java macro played on them
+///
+/// The paths may use the placeholders of the macro templates and the relative paths are relative to the campaign file,
+/// except the artifact paths that are relative to the working directory of the macro
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Campaign {
+    /// Directory of the sims
+    pub source: PathBuf,
+    /// Directory the artifacts are collected into
+    pub destination: Option<PathBuf>,
+    /// Java macro
+    #[serde(rename = "macro")]
+    pub java: Option<PathBuf>,
+    /// StarCCM+ version pinned for all the cases, overriding the versions pinned in the configuration
+    pub version: Option<String>,
+    pub cases: CaseMatrix,
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+    #[serde(default)]
+    pub progress: Progress,
+}
+impl Campaign {
+    /// Reads a campaign TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).map_err(|e| CampaignError::Read(path.to_path_buf(), e))?;
+        let mut campaign: Self =
+            toml::from_str(&text).map_err(|e| CampaignError::Parse(path.to_path_buf(), e))?;
+        if let Some((pattern, e)) = campaign
+            .cases
+            .exclude
+            .iter()
+            .find_map(|pattern| glob::Pattern::new(pattern).err().map(|e| (pattern, e)))
+        {
+            return Err(CampaignError::Exclude(pattern.clone(), e));
+        }
+        if campaign.cases().is_empty() {
+            return Err(CampaignError::Empty);
+        }
+        if let Some(dir) = path.parent() {
+            campaign.source = dir.join(&campaign.source);
+            campaign.destination = campaign
+                .destination
+                .map(|destination| dir.join(destination));
+            campaign.java = campaign.java.map(|java| dir.join(java));
+            let progress = &mut campaign.progress;
+            for marker in [&mut progress.solved, &mut progress.post_processed] {
+                *marker = marker.take().map(|marker| join_marker(dir, marker));
+            }
+        }
+        Ok(campaign)
+    }
+    /// Case names of the campaign
+    pub fn cases(&self) -> Vec<String> {
+        self.cases.cases()
+    }
+    /// Path to the sim of a case
+    pub fn sim(&self, case: &str) -> PathBuf {
+        self.source.join(case).with_extension("sim")
+    }
+    /// Java macro of the campaign
+    pub fn java(&self) -> Result<&Path> {
+        self.java.as_deref().ok_or(CampaignError::Missing("macro"))
+    }
+    /// Directory the artifacts are collected into
+    pub fn destination(&self) -> Result<&Path> {
+        self.destination
+            .as_deref()
+            .ok_or(CampaignError::Missing("destination"))
+    }
+    /// Status of a case, `root` being the root directory of its checklist folder
+    pub fn status(&self, case: &str, root: &Path, variables: &Variables) -> Result<CaseStatus> {
+        let reached = |marker: Option<&String>| -> Result<bool> {
+            Ok(match marker {
+                Some(marker) => Path::new(&self.render(marker, case, variables)?).exists(),
+                None => false,
+            })
+        };
+        if reached(self.progress.post_processed.as_ref())? {
+            return Ok(CaseStatus::PostProcessed);
+        }
+        if reached(self.progress.solved.as_ref())? {
+            return Ok(CaseStatus::Solved);
+        }
+        Ok(match CaseFolder::new(root, case).verdict() {
+            Some(Verdict::Pass) => CaseStatus::Pass,
+            Some(Verdict::Fail) => CaseStatus::Fail,
+            None if self.sim(case).is_file() => CaseStatus::NotChecked,
+            None => CaseStatus::Missing,
+        })
+    }
+    /// Sims of the source directory that are not cases of the campaign
+    pub fn extra_sims(&self) -> Result<Vec<PathBuf>> {
+        let cases: BTreeSet<_> = self.cases().into_iter().collect();
+        let entries = fs::read_dir(&self.source)
+            .map_err(|e| CampaignError::Source(self.source.clone(), e))?;
+        let mut extra = vec![];
+        for entry in entries {
+            let path = entry
+                .map_err(|e| CampaignError::Source(self.source.clone(), e))?
+                .path();
+            if path.is_file()
+                && path.extension().is_some_and(|ext| ext == "sim")
+                && !path
+                    .file_stem()
+                    .is_some_and(|stem| cases.contains(stem.to_string_lossy().as_ref()))
+            {
+                extra.push(path);
+            }
+        }
+        extra.sort();
+        Ok(extra)
+    }
+    fn render(&self, text: &str, case: &str, variables: &Variables) -> Result<String> {
+        variables
+            .render(text)
+            .map_err(|placeholder| CampaignError::Placeholder {
+                text: text.to_string(),
+                placeholder,
+                case: case.to_string(),
+            })
+    }
+    /// Artifacts of a case, with their placeholders replaced
+    ///
+    /// `{{work_dir}}` is kept, to be replaced by the working directory of each run
+    pub fn artifacts(&self, case: &str, variables: &Variables) -> Result<Vec<Artifact>> {
+        if !self.artifacts.is_empty() {
+            self.destination()?;
+        }
+        let variables = variables.clone().work_dir(Path::new("{{work_dir}}"));
+        self.artifacts
+            .iter()
+            .map(|artifact| {
+                artifact
+                    .render(&variables)
+                    .map_err(|placeholder| CampaignError::Placeholder {
+                        text: artifact.from.to_string_lossy().into_owned(),
+                        placeholder,
+                        case: case.to_string(),
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn matrix(wind_speed: Vec<u32>, exclude: &[&str]) -> CaseMatrix {
+        CaseMatrix {
+            zen: vec![30, 60],
+            az: vec![0, 45],
+            config: vec!["OS".to_string(), "CS".to_string()],
+            wind_speed,
+            exclude: exclude.iter().map(|pattern| pattern.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn cases_by_config_wind_speed_az_and_zen() {
+        let cases = matrix(vec![7, 17], &[]).cases();
+        assert_eq!(cases.len(), 16);
+        assert_eq!(
+            cases[..5],
+            [
+                "zen30az000_OS_7ms",
+                "zen60az000_OS_7ms",
+                "zen30az045_OS_7ms",
+                "zen60az045_OS_7ms",
+                "zen30az000_OS_17ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn cases_with_exclusions() {
+        let cases = matrix(vec![7, 17], &["*_OS_17ms", "zen60az045_*"]).cases();
+        assert_eq!(
+            cases,
+            [
+                "zen30az000_OS_7ms",
+                "zen60az000_OS_7ms",
+                "zen30az045_OS_7ms",
+                "zen30az000_CS_7ms",
+                "zen60az000_CS_7ms",
+                "zen30az045_CS_7ms",
+                "zen30az000_CS_17ms",
+                "zen60az000_CS_17ms",
+                "zen30az045_CS_17ms",
+            ]
+        );
+        assert!(matrix(vec![], &["*"]).cases().is_empty());
+    }
+
+    #[test]
+    fn load_the_version_pin() {
+        let dir = TempDir::new("campaign_version");
+        let path = dir.join("campaign.toml");
+        fs::write(
+            &path,
+            r#"
+source = "sims"
+macro = "update.java"
+version = "17.06.007"
+[cases]
+zen = [30]
+az = [0]
+config = ["OS_7ms"]
+"#,
+        )
+        .unwrap();
+        let campaign = Campaign::load(&path).unwrap();
+        assert_eq!(campaign.version.as_deref(), Some("17.06.007"));
+        assert_eq!(
+            campaign.sim("zen30az000_OS_7ms"),
+            dir.join("sims/zen30az000_OS_7ms.sim")
+        );
+    }
+
+    #[test]
+    fn load_relative_markers() {
+        let dir = TempDir::new("campaign_markers");
+        let path = dir.join("campaign.toml");
+        fs::write(
+            &path,
+            r#"
+source = "sims"
+[cases]
+zen = [30]
+az = [0]
+config = ["OS_7ms"]
+[progress]
+solved = "solved/{{case}}.sim"
+post_processed = "{{ output_dir }}/{{case}}@PASS/RI_tel.png"
+"#,
+        )
+        .unwrap();
+        let campaign = Campaign::load(&path).unwrap();
+        assert_eq!(
+            campaign.progress,
+            Progress {
+                solved: Some(
+                    dir.join("solved/{{case}}.sim")
+                        .to_string_lossy()
+                        .into_owned()
+                ),
+                post_processed: Some("{{ output_dir }}/{{case}}@PASS/RI_tel.png".to_string()),
+            }
+        );
+        assert_eq!(
+            join_marker(&dir, "/mnt/solved/{{case}}.sim".to_string()),
+            "/mnt/solved/{{case}}.sim"
+        );
+    }
+
+    #[test]
+    fn artifacts_keep_the_work_dir() {
+        let campaign = Campaign {
+            source: PathBuf::from("sims"),
+            destination: Some(PathBuf::from("dest")),
+            java: None,
+            version: None,
+            cases: matrix(vec![], &[]),
+            artifacts: vec![Artifact::new(
+                "{{work_dir}}/{{case}}.sim",
+                Some("{{config}}/{{case}}.sim".to_string()),
+            )],
+            progress: Progress::default(),
+        };
+        let case = "zen30az000_OS";
+        let variables = Variables::new(case, Path::new("/out"));
+        assert_eq!(
+            campaign.artifacts(case, &variables).unwrap(),
+            [Artifact::new(
+                "{{work_dir}}/zen30az000_OS.sim",
+                Some("OS/zen30az000_OS.sim".to_string()),
+            )]
+        );
+    }
+}
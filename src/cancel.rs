@@ -0,0 +1,113 @@
+use std::{
+    collections::BTreeSet,
+    process::{self, Child},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+/// Process groups of the running StarCCM+ processes
+static GROUPS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+/// Time given to the StarCCM+ processes to exit after `SIGTERM` on a second Ctrl-C
+const EXIT_GRACE: Duration = Duration::from_secs(5);
+
+/// Cancels all the StarCCM+ runs
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+/// Checks if the StarCCM+ runs have been cancelled
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Process group of a child started with `process_group(0)`, tracked until it is dropped
+///
+/// The processes of the tracked groups do not get the Ctrl-C of the terminal,
+/// they are terminated when the CLI exits on a second Ctrl-C
+#[derive(Debug)]
+pub struct ProcessGroup(u32);
+impl ProcessGroup {
+    pub fn track(child: &Child) -> Self {
+        GROUPS.lock().unwrap().insert(child.id());
+        Self(child.id())
+    }
+}
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        GROUPS.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Sends `SIGTERM` to the processes of the tracked groups and then `SIGKILL` if they are still running after `grace`
+fn terminate_groups(grace: Duration) {
+    #[cfg(unix)]
+    {
+        let groups: Vec<_> = GROUPS
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&pgid| pgid as libc::pid_t)
+            .collect();
+        for &pgid in &groups {
+            unsafe { libc::killpg(pgid, libc::SIGTERM) };
+        }
+        let now = Instant::now();
+        while now.elapsed() < grace
+            && groups
+                .iter()
+                .any(|&pgid| unsafe { libc::killpg(pgid, 0) } == 0)
+        {
+            thread::sleep(Duration::from_millis(100));
+        }
+        for &pgid in &groups {
+            unsafe { libc::killpg(pgid, libc::SIGKILL) };
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = grace;
+}
+
+/// Cancels the StarCCM+ runs on Ctrl-C
+///
+/// A second Ctrl-C terminates the StarCCM+ processes and exits
+pub fn handle_interrupts() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if is_cancelled() {
+            eprintln!("terminating the StarCCM+ processes");
+            terminate_groups(EXIT_GRACE);
+            process::exit(130);
+        }
+        eprintln!("cancelling the StarCCM+ runs (Ctrl-C again to exit now)");
+        cancel();
+    })
+}
+
+/// Terminates a child process started in its own process group and all the processes of that group
+///
+/// The processes are sent `SIGTERM` and then `SIGKILL` if they are still running after `grace`
+pub fn kill_group(child: &mut Child, grace: Duration) {
+    #[cfg(unix)]
+    {
+        let pgid = child.id() as libc::pid_t;
+        unsafe { libc::killpg(pgid, libc::SIGTERM) };
+        let now = Instant::now();
+        while now.elapsed() < grace {
+            if let Ok(Some(_)) = child.try_wait() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        // the server processes may outlive the client
+        unsafe { libc::killpg(pgid, libc::SIGKILL) };
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = grace;
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+}
@@ -74,13 +74,7 @@ impl<'a> Display for Check<'a> {
 pub struct CheckList<'a>(pub(crate) Vec<Check<'a>>);
 impl<'a> CheckList<'a> {
     pub fn pass(&self) -> bool {
-        !self.0.iter().any(|check| {
-            if let Check::Fail(_) = check {
-                true
-            } else {
-                false
-            }
-        })
+        !self.0.iter().any(|check| matches!(check, Check::Fail(_)))
     }
 }
 impl<'a> Display for CheckList<'a> {
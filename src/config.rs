@@ -0,0 +1,631 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs, io,
+    path::{self, Path, PathBuf},
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::{
+    FailureKind, PatternConfig, Recheck, RetryPolicy, SchedulerKind, ZERO_EXIT_FAILURES, cache_dir,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read the configuration file {0:?}")]
+    Read(PathBuf, #[source] io::Error),
+    #[error("failed to parse the configuration file {0:?}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("invalid configuration")]
+    Invalid(#[from] toml::de::Error),
+    #[error("failed to serialize the configuration")]
+    Serialize(#[from] toml::ser::Error),
+}
+type Result<T> = std::result::Result<T, ConfigError>;
+
+/// Layout of the checklist folders in the output root directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// the checklist folders are written into a sub-directory named after the directory of the sims
+    #[default]
+    Nested,
+    /// the checklist folders are all written into the output root directory
+    Flat,
+}
+
+/// StarCCM+ license modes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseMode {
+    /// power-on-demand license
+    #[default]
+    Pod,
+    /// license checked out from the site license servers
+    Server,
+    /// no license
+    None,
+}
+
+/// Ways of passing the power-on-demand key to StarCCM+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PodkeyVia {
+    /// `LM_PROJECT` environment variable of the StarCCM+ process, hidden from the other users
+    #[default]
+    Env,
+    /// `-podkey` command line argument, visible to all the users in `ps`
+    Args,
+}
+
+/// StarCCM+ installations configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StarccmConfig {
+    /// Path to the default StarCCM+ binary
+    pub binary: PathBuf,
+    /// StarCCM+ version pinned for all the sims (usually set in the project configuration file of a campaign)
+    pub version: Option<String>,
+    /// StarCCM+ versions pinned for individual sims, indexed by case name
+    pub pins: BTreeMap<String, String>,
+    /// Directories scanned for `*/STAR-CCM+*/star/bin/starccm+`
+    pub search: Vec<PathBuf>,
+    /// Paths to the StarCCM+ binaries, indexed by version
+    pub installations: BTreeMap<String, PathBuf>,
+    /// Time after which a StarCCM+ run is killed, in seconds
+    pub timeout: Option<u64>,
+    /// Failures detected in the output of a run that has exited with code 0
+    pub zero_exit_failures: Vec<FailureKind>,
+}
+impl Default for StarccmConfig {
+    fn default() -> Self {
+        Self {
+            binary: PathBuf::from("/opt/Siemens/17.06.007/STAR-CCM+17.06.007/star/bin/starccm+"),
+            version: None,
+            pins: BTreeMap::new(),
+            search: vec![PathBuf::from("/opt/Siemens")],
+            installations: BTreeMap::new(),
+            timeout: None,
+            zero_exit_failures: ZERO_EXIT_FAILURES.to_vec(),
+        }
+    }
+}
+impl StarccmConfig {
+    /// StarCCM+ version pinned for a case
+    pub fn pinned(&self, case: &str) -> Option<&str> {
+        self.pins
+            .get(case)
+            .or(self.version.as_ref())
+            .map(|v| v.as_str())
+    }
+}
+
+/// StarCCM+ license configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LicenseConfig {
+    pub mode: LicenseMode,
+    /// License server as `port@host`
+    pub server: String,
+    /// License servers tried, in order, if the license server is not available
+    pub fallbacks: Vec<String>,
+    /// File with the power-on-demand key, readable only by its owner
+    pub podkey_file: Option<PathBuf>,
+    /// Shell command printing the power-on-demand key, e.g. a credentials helper
+    pub podkey_command: Option<String>,
+    /// How the power-on-demand key is passed to StarCCM+
+    pub podkey_via: PodkeyVia,
+    /// Number of StarCCM+ runs the license allows at once, bounding the concurrent local runs
+    pub seats: Option<usize>,
+}
+impl Default for LicenseConfig {
+    fn default() -> Self {
+        Self {
+            mode: LicenseMode::default(),
+            server: "1999@flex.cd-adapco.com".to_string(),
+            fallbacks: vec![],
+            podkey_file: None,
+            podkey_command: None,
+            podkey_via: PodkeyVia::default(),
+            seats: None,
+        }
+    }
+}
+
+/// StarCCM+ parallel execution settings
+///
+/// e.g. a configuration profile:
+/// ```toml
+/// [profiles.remesh]
+/// np = 32
+/// mpi = "openmpi"
+/// machinefile = "/home/ubuntu/machines.txt"
+/// args = ["-cpubind"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Parallel {
+    /// Number of processes (`-np`)
+    pub np: Option<usize>,
+    /// MPI driver (`-mpi`)
+    pub mpi: Option<String>,
+    /// File with the hosts of the processes (`-machinefile`)
+    pub machinefile: Option<PathBuf>,
+    /// Extra arguments passed as is to StarCCM+
+    pub args: Vec<String>,
+}
+impl Parallel {
+    /// Overrides the settings with the ones set in `other`, the extra arguments being appended
+    pub fn merge(mut self, other: Parallel) -> Self {
+        self.np = other.np.or(self.np);
+        self.mpi = other.mpi.or(self.mpi);
+        self.machinefile = other.machinefile.or(self.machinefile);
+        self.args.extend(other.args);
+        self
+    }
+    /// Makes the machine file and the extra arguments naming existing files absolute,
+    /// as StarCCM+ runs in the working directory of the run
+    pub fn absolute(mut self) -> Self {
+        self.machinefile = self
+            .machinefile
+            .map(|path| path::absolute(&path).unwrap_or(path));
+        for arg in self.args.iter_mut() {
+            let path = Path::new(arg.as_str());
+            if !arg.starts_with('-')
+                && path.is_relative()
+                && path.exists()
+                && let Ok(path) = path::absolute(path)
+            {
+                *arg = path.to_string_lossy().into_owned();
+            }
+        }
+        self
+    }
+    /// StarCCM+ command line arguments
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(np) = self.np {
+            args.extend(["-np".to_string(), np.to_string()]);
+        }
+        if let Some(mpi) = self.mpi.as_ref() {
+            args.extend(["-mpi".to_string(), mpi.clone()]);
+        }
+        if let Some(machinefile) = self.machinefile.as_ref() {
+            args.extend([
+                "-machinefile".to_string(),
+                machinefile.to_string_lossy().into_owned(),
+            ]);
+        }
+        args.extend(self.args.iter().cloned());
+        args
+    }
+}
+
+/// Job scheduler configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// Scheduler the macros are submitted to, the macros are run directly if it is not set
+    pub kind: Option<SchedulerKind>,
+    /// Interval between two polls of the status of a job, in seconds
+    pub poll: u64,
+    /// Extra directives written in the job scripts, e.g. `--partition=cfd` for Slurm
+    pub directives: Vec<String>,
+    pub sbatch: PathBuf,
+    pub squeue: PathBuf,
+    pub sacct: PathBuf,
+    pub scancel: PathBuf,
+    pub qsub: PathBuf,
+    pub qstat: PathBuf,
+    pub qdel: PathBuf,
+}
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            poll: 30,
+            directives: vec![],
+            sbatch: PathBuf::from("sbatch"),
+            squeue: PathBuf::from("squeue"),
+            sacct: PathBuf::from("sacct"),
+            scancel: PathBuf::from("scancel"),
+            qsub: PathBuf::from("qsub"),
+            qstat: PathBuf::from("qstat"),
+            qdel: PathBuf::from("qdel"),
+        }
+    }
+}
+
+/// Java macros configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MacrosConfig {
+    /// Directory of the customized `report.java` and `scenes_views.java` macros, the bundled macros are used otherwise
+    pub dir: PathBuf,
+}
+impl Default for MacrosConfig {
+    fn default() -> Self {
+        Self { dir: desktop_dir() }
+    }
+}
+
+/// Output directories configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Root directory of the checklist folders
+    pub root: PathBuf,
+    /// Directory where the StarCCM+ scenes macro writes the hardcopies
+    pub scenes: PathBuf,
+    /// Layout of the checklist folders
+    pub layout: Layout,
+}
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            root: desktop_dir(),
+            scenes: desktop_dir(),
+            layout: Layout::default(),
+        }
+    }
+}
+
+/// Default options of the `check` command
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckConfig {
+    /// Writes the checklists to folders
+    pub folder: bool,
+    /// Generates the scenes hardcopies
+    pub scenes: bool,
+    /// Number of sims checked concurrently
+    pub jobs: usize,
+    /// Re-check mode of the sims already checked
+    pub recheck: Recheck,
+    /// Compares the content hash of the sims to the cached reports
+    pub hash: bool,
+}
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self {
+            folder: false,
+            scenes: true,
+            jobs: 1,
+            recheck: Recheck::default(),
+            hash: false,
+        }
+    }
+}
+
+/// Report cache configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Directory of the cached summary reports
+    pub dir: PathBuf,
+}
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: cache_dir().join("reports"),
+        }
+    }
+}
+
+/// Origin of a configuration value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    /// `/etc/cfdsim/config.toml`
+    System(PathBuf),
+    /// `$XDG_CONFIG_HOME/cfdsim/config.toml`
+    User(PathBuf),
+    /// `cfdsim.toml` in the current directory or in one of its parents
+    Project(PathBuf),
+    /// configuration file given on the command line
+    File(PathBuf),
+    /// environment variable
+    Env(&'static str),
+}
+impl Source {
+    /// Path to the configuration file of the source
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Source::System(path)
+            | Source::User(path)
+            | Source::Project(path)
+            | Source::File(path) => Some(path),
+            Source::Default | Source::Env(_) => None,
+        }
+    }
+}
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::System(path) => write!(f, "system: {}", path.display()),
+            Source::User(path) => write!(f, "user: {}", path.display()),
+            Source::Project(path) => write!(f, "project: {}", path.display()),
+            Source::File(path) => write!(f, "file: {}", path.display()),
+            Source::Env(var) => write!(f, "env: {var}"),
+        }
+    }
+}
+
+/// Environment variables overriding configuration values
+const ENV_OVERRIDES: [(&str, &str); 9] = [
+    ("STARCCM", "starccm.binary"),
+    ("STARCCM_VERSION", "starccm.version"),
+    ("STARCCM_MACROS", "macros.dir"),
+    ("CFDSIM_LICENSE_MODE", "license.mode"),
+    ("CFDSIM_LICENSE_SERVER", "license.server"),
+    ("CFDSIM_OUTPUT", "output.root"),
+    ("CFDSIM_SCENES", "output.scenes"),
+    ("CFDSIM_SCHEDULER", "scheduler.kind"),
+    ("CFDSIM_CACHE", "cache.dir"),
+];
+
+/// `cfdsim` configuration
+///
+/// The configuration is layered, each layer overriding the values of the previous ones:
+///  1. the system configuration file `/etc/cfdsim/config.toml`,
+///  2. the user configuration file `$XDG_CONFIG_HOME/cfdsim/config.toml` (or `~/.config/cfdsim/config.toml`),
+///  3. the project configuration file `cfdsim.toml` in the current directory or in one of its parents,
+///  4. the configuration file given on the command line,
+///  5. the environment variables `STARCCM`, `STARCCM_VERSION`, `STARCCM_MACROS`, `CFDSIM_LICENSE_MODE`,
+///     `CFDSIM_LICENSE_SERVER`, `CFDSIM_OUTPUT`, `CFDSIM_SCENES`, `CFDSIM_SCHEDULER` and `CFDSIM_CACHE`.
+///
+/// e.g.
+/// ```toml
+/// [starccm]
+/// binary = "/opt/Siemens/18.04.008/STAR-CCM+18.04.008/star/bin/starccm+"
+/// version = "17.06.007"
+///
+/// [starccm.pins]
+/// zen30az000_OS_7ms = "18.04.008"
+///
+/// [license]
+/// mode = "server"
+/// server = "1999@flexlm.example.org"
+/// fallbacks = ["1999@flexlm-backup.example.org"]
+///
+/// [output]
+/// root = "/data/cfd/checklists"
+/// layout = "flat"
+///
+/// [check]
+/// folder = true
+/// jobs = 4
+///
+/// [profiles.solve]
+/// np = 16
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub starccm: StarccmConfig,
+    pub license: LicenseConfig,
+    pub macros: MacrosConfig,
+    pub output: OutputConfig,
+    pub check: CheckConfig,
+    pub cache: CacheConfig,
+    pub scheduler: SchedulerConfig,
+    /// StarCCM+ parallel execution profiles, indexed by name
+    pub profiles: BTreeMap<String, Parallel>,
+    pub retry: RetryPolicy,
+    /// User patterns of the StarCCM+ log analyzer, indexed by name
+    pub patterns: BTreeMap<String, PatternConfig>,
+    #[serde(skip)]
+    sources: BTreeMap<String, Source>,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Global configuration
+///
+/// If it has not been set with [Config::set], the configuration is loaded on first use
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        Config::load(None).unwrap_or_else(|e| {
+            eprintln!("{e}, using the default configuration");
+            Config::default()
+        })
+    })
+}
+
+impl Config {
+    /// Loads the configuration layers, `path` being an optional configuration file given on the command line
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut layers = vec![];
+        let system = PathBuf::from("/etc/cfdsim/config.toml");
+        if system.is_file() {
+            layers.push(Source::System(system));
+        }
+        let user = config_dir().join("config.toml");
+        if user.is_file() {
+            layers.push(Source::User(user));
+        }
+        if let Some(project) = project_file() {
+            layers.push(Source::Project(project));
+        }
+        if let Some(path) = path {
+            layers.push(Source::File(path.to_path_buf()));
+        }
+
+        let mut merged = Table::new();
+        let mut sources = BTreeMap::new();
+        for source in layers {
+            let path = source.path().unwrap();
+            let layer = fs::read_to_string(path)
+                .map_err(|e| ConfigError::Read(path.to_path_buf(), e))?
+                .parse::<Table>()
+                .map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+            // checking each layer on its own for error messages pointing at the faulty file
+            Config::deserialize(Value::Table(layer.clone()))
+                .map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+            merge(&mut merged, layer, &source, &mut sources, "");
+        }
+        for (var, key) in ENV_OVERRIDES {
+            if let Ok(value) = env::var(var) {
+                let (section, name) = key.split_once('.').unwrap();
+                let mut layer = Table::new();
+                layer.insert(
+                    section.to_string(),
+                    Value::Table(Table::from_iter([(name.to_string(), Value::String(value))])),
+                );
+                merge(&mut merged, layer, &Source::Env(var), &mut sources, "");
+            }
+        }
+
+        let mut config = Config::deserialize(Value::Table(merged))?;
+        config.sources = sources;
+        Ok(config)
+    }
+    /// Sets the global configuration
+    ///
+    /// Has no effect if the global configuration is already set
+    pub fn set(self) {
+        let _ = CONFIG.set(self);
+    }
+    /// Origin of the value of the configuration `key` (e.g. `starccm.binary`)
+    pub fn source(&self, key: &str) -> &Source {
+        self.sources.get(key).unwrap_or(&Source::Default)
+    }
+    /// Effective configuration values as `(key, value, origin)`
+    pub fn values(&self) -> Result<Vec<(String, String, &Source)>> {
+        let mut values = vec![];
+        flatten(&Value::try_from(self)?, "", &mut values);
+        Ok(values
+            .into_iter()
+            .map(|(key, value)| {
+                let source = self.source(&key);
+                (key, value, source)
+            })
+            .collect())
+    }
+}
+
+/// Merges `layer` into `base`, recording the `source` of each value of `layer`
+fn merge(
+    base: &mut Table,
+    layer: Table,
+    source: &Source,
+    sources: &mut BTreeMap<String, Source>,
+    prefix: &str,
+) {
+    for (key, value) in layer {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(layer)) => {
+                merge(base, layer, source, sources, &path)
+            }
+            (_, value) => {
+                let mut leaves = vec![];
+                flatten(&value, &path, &mut leaves);
+                for (key, _) in leaves {
+                    sources.insert(key, source.clone());
+                }
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Flattens the tables of `value` into `(dotted key, value)`
+fn flatten(value: &Value, prefix: &str, leaves: &mut Vec<(String, String)>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(value, &path, leaves);
+            }
+        }
+        value => leaves.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+/// Nearest `cfdsim.toml` file in the current directory or in one of its parents
+fn project_file() -> Option<PathBuf> {
+    env::current_dir()
+        .ok()?
+        .ancestors()
+        .map(|dir| dir.join("cfdsim.toml"))
+        .find(|path| path.is_file())
+}
+
+/// Home directory of the user running the process
+pub fn home_dir() -> PathBuf {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+}
+
+/// Desktop directory: `$XDG_DESKTOP_DIR` or `~/Desktop`
+pub fn desktop_dir() -> PathBuf {
+    env::var_os("XDG_DESKTOP_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join("Desktop"))
+}
+
+/// User configuration directory: `$XDG_CONFIG_HOME/cfdsim` or `~/.config/cfdsim`
+pub fn config_dir() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".config"))
+        .join("cfdsim")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn parallel_paths_from_a_work_dir() {
+        // the tests run in the package directory
+        let parallel = Parallel {
+            np: Some(4),
+            mpi: None,
+            machinefile: Some(PathBuf::from("Cargo.toml")),
+            args: vec![
+                "-cpubind".to_string(),
+                "Cargo.toml".to_string(),
+                "v".to_string(),
+            ],
+        }
+        .absolute();
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert_eq!(parallel.machinefile.as_deref(), Some(manifest.as_path()));
+        let args = parallel.args();
+        assert_eq!(
+            args[2..],
+            [
+                "-machinefile",
+                &manifest.to_string_lossy(),
+                "-cpubind",
+                &manifest.to_string_lossy(),
+                "v"
+            ]
+        );
+        let work_dir = TempDir::new("config_parallel");
+        let status = Command::new("cat")
+            .args([&args[3], &args[5]])
+            .current_dir(&*work_dir)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
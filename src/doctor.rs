@@ -0,0 +1,341 @@
+use std::{
+    env,
+    fmt::{self, Display},
+    fs,
+    io::{self, Read},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{BUNDLED_MACROS, Config, License, LicenseError, LicenseMode, LogAnalyzer, MacroStatus};
+
+/// Health of a prerequisite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    Warning,
+    Fail,
+}
+
+/// Diagnostic of one of the prerequisites of the CLI
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub name: &'static str,
+    pub health: Health,
+    pub message: String,
+    /// how to fix the problem
+    pub fix: Option<String>,
+}
+impl Diagnostic {
+    fn ok(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            health: Health::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+    fn warning(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            health: Health::Warning,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+    fn fail(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            health: Health::Fail,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = match self.health {
+            Health::Ok => " OK ",
+            Health::Warning => "WARN",
+            Health::Fail => "FAIL",
+        };
+        write!(f, "[{tag}] {:<16} {}", self.name, self.message)?;
+        if let Some(fix) = self.fix.as_ref() {
+            write!(f, "\n       {:<16} fix: {fix}", "")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the prerequisites of the CLI for the given configuration
+pub fn diagnose(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    diagnostics.extend(binary(&config.starccm.binary));
+    diagnostics.push(macros(&config.macros.dir));
+    diagnostics.push(writable(
+        "output root",
+        &config.output.root,
+        "output.root",
+        false,
+    ));
+    diagnostics.push(writable(
+        "scenes source",
+        &config.output.scenes,
+        "output.scenes",
+        true,
+    ));
+    diagnostics.push(writable(
+        "report cache",
+        &config.cache.dir,
+        "cache.dir",
+        false,
+    ));
+    diagnostics.push(license(config));
+    if !config.patterns.is_empty() {
+        diagnostics.push(match LogAnalyzer::new(&config.patterns) {
+            Ok(_) => Diagnostic::ok(
+                "log patterns",
+                format!("{} user patterns", config.patterns.len()),
+            ),
+            Err(e) => Diagnostic::fail(
+                "log patterns",
+                format!(
+                    "{e}: {}",
+                    std::error::Error::source(&e)
+                        .map(|e| e.to_string())
+                        .unwrap_or_default()
+                ),
+                "fix the regular expression in the patterns configuration",
+            ),
+        });
+    }
+    diagnostics
+}
+
+fn binary(binary: &Path) -> Vec<Diagnostic> {
+    let name = "starccm+ binary";
+    let fix = "set the path to the StarCCM+ binary with the STARCCM environment variable or the starccm.binary configuration key";
+    let metadata = match fs::metadata(binary) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => {
+            return vec![Diagnostic::fail(
+                name,
+                format!("{binary:?} is not a file"),
+                fix,
+            )];
+        }
+        Err(e) => {
+            return vec![Diagnostic::fail(
+                name,
+                format!("{binary:?} not found ({e})"),
+                fix,
+            )];
+        }
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return vec![Diagnostic::fail(
+                name,
+                format!("{binary:?} is not executable"),
+                format!("chmod +x {}", binary.display()),
+            )];
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    let version = match starccm_version(binary, Duration::from_secs(60)) {
+        Ok(Some(version)) => Diagnostic::ok("starccm+ version", version),
+        Ok(None) => Diagnostic::warning(
+            "starccm+ version",
+            format!(
+                "failed to read the version from `{} -version`",
+                binary.display()
+            ),
+            format!("check that `{} -version` runs", binary.display()),
+        ),
+        Err(e) => Diagnostic::fail(
+            "starccm+ version",
+            format!("`{} -version` failed: {e}", binary.display()),
+            format!("check that `{} -version` runs", binary.display()),
+        ),
+    };
+    vec![Diagnostic::ok(name, binary.display().to_string()), version]
+}
+
+/// Reads the StarCCM+ version from the output of `starccm+ -version`
+pub fn starccm_version(binary: &Path, timeout: Duration) -> io::Result<Option<String>> {
+    let mut child = Command::new(binary)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let now = Instant::now();
+    while child.try_wait()?.is_none() {
+        if now.elapsed() > timeout {
+            child.kill()?;
+            child.wait()?;
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no answer after {}s", timeout.as_secs()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let mut stdout = String::new();
+    if let Some(mut out) = child.stdout.take() {
+        out.read_to_string(&mut stdout)?;
+    }
+    Ok(parse_version(&stdout))
+}
+
+/// Finds a version number like `17.06.007` in `text`
+pub fn parse_version(text: &str) -> Option<String> {
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map(|word| word.trim_matches('.'))
+        .find(|word| {
+            let numbers: Vec<_> = word.split('.').collect();
+            numbers.len() >= 3 && numbers.iter().all(|n| !n.is_empty())
+        })
+        .map(str::to_string)
+}
+
+fn macros(dir: &Path) -> Diagnostic {
+    let name = "java macros";
+    let mut statuses = vec![];
+    let mut outdated = vec![];
+    for bundled in BUNDLED_MACROS {
+        match bundled.status(dir) {
+            Ok(status) => {
+                if let MacroStatus::Outdated(_) = status {
+                    outdated.push(bundled.name);
+                }
+                statuses.push(format!("{} {status}", bundled.name));
+            }
+            Err(e) => {
+                return Diagnostic::fail(
+                    name,
+                    e.to_string(),
+                    "fix the permissions of the macros directory or set the directory of the macros with the STARCCM_MACROS environment variable or the macros.dir configuration key",
+                );
+            }
+        }
+    }
+    let message = format!("{} in {dir:?}", statuses.join(", "));
+    if outdated.is_empty() {
+        Diagnostic::ok(name, message)
+    } else {
+        Diagnostic::warning(
+            name,
+            format!(
+                "{message}, the outdated copies of {} are ignored",
+                outdated.join(" and ")
+            ),
+            "update the exported macros with `cfdsim macros export`",
+        )
+    }
+}
+
+/// Checks that `dir` is writable
+///
+/// A missing directory is a failure only if it is `required`, otherwise it will be created when needed
+fn writable(name: &'static str, dir: &Path, setting: &str, required: bool) -> Diagnostic {
+    if !dir.is_dir() {
+        let message = format!("{dir:?} does not exist");
+        let fix = format!(
+            "mkdir -p {} or choose another directory with {setting}",
+            dir.display()
+        );
+        return if required {
+            Diagnostic::fail(name, message, fix)
+        } else {
+            Diagnostic::warning(name, message, fix)
+        };
+    }
+    let probe = dir.join(format!(".cfdsim-doctor-{}", std::process::id()));
+    match fs::write(&probe, b"") {
+        Ok(_) => {
+            let _ = fs::remove_file(probe);
+            Diagnostic::ok(name, dir.display().to_string())
+        }
+        Err(e) => Diagnostic::fail(
+            name,
+            format!("{dir:?} is not writable ({e})"),
+            format!(
+                "give write permission to {} or choose another directory with {setting}",
+                env::var("USER").unwrap_or("the user".to_string())
+            ),
+        ),
+    }
+}
+
+/// Checks that a license server is given as `port@host`
+fn is_license_server(server: &str) -> bool {
+    server
+        .split_once('@')
+        .is_some_and(|(port, host)| port.parse::<u16>().is_ok() && !host.is_empty())
+}
+
+fn license(config: &Config) -> Diagnostic {
+    let name = "license";
+    if config.license.mode == LicenseMode::None {
+        return Diagnostic::ok(name, "no license");
+    }
+    if let Some(server) = Some(&config.license.server)
+        .into_iter()
+        .chain(config.license.fallbacks.iter())
+        .find(|server| !is_license_server(server))
+    {
+        return Diagnostic::fail(
+            name,
+            format!(r#"license server "{server}" is not "port@host""#),
+            "set the license servers with the license.server and license.fallbacks configuration keys, e.g. 1999@flex.cd-adapco.com",
+        );
+    }
+    match License::new(&config.license) {
+        Ok(License::PowerOnDemand { podkey, .. })
+            if !podkey.expose().chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            Diagnostic::fail(
+                name,
+                "the power-on-demand key is not valid",
+                "check the power-on-demand key",
+            )
+        }
+        Ok(license) => Diagnostic::ok(name, license.to_string()),
+        Err(LicenseError::Permissions(path, mode)) => Diagnostic::fail(
+            name,
+            LicenseError::Permissions(path.clone(), mode).to_string(),
+            format!("chmod 600 {}", path.display()),
+        ),
+        Err(e) => Diagnostic::fail(
+            name,
+            e.to_string(),
+            "set the power-on-demand key with license.podkey_file, license.podkey_command or the PODKEY environment variable",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(
+            parse_version("STAR-CCM+ 17.06.007 Build 17.06.007 (linux-x86_64-2.17/gnu9.2)"),
+            Some("17.06.007".to_string())
+        );
+        assert_eq!(
+            parse_version("STAR-CCM+18.04.008-R8").as_deref(),
+            Some("18.04.008")
+        );
+        assert_eq!(parse_version("version 2.17.").as_deref(), None);
+        assert_eq!(parse_version("...1.2.3...").as_deref(), Some("1.2.3"));
+        assert_eq!(parse_version("no version"), None);
+    }
+}
@@ -24,7 +24,7 @@ impl<'a, const N: usize> From<[&'a str; N]> for Expectation<'a> {
 }
 impl<'a> PartialEq<str> for Expectation<'a> {
     fn eq(&self, other: &str) -> bool {
-        self.0.iter().any(|&v| v == other)
+        self.0.contains(&other)
     }
 }
 impl<'a> fmt::Display for Expectation<'a> {
@@ -0,0 +1,148 @@
+use std::{
+    fmt::{self, Display},
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{Config, parse_version};
+
+/// StarCCM+ installation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Installation {
+    pub version: String,
+    pub binary: PathBuf,
+}
+impl Display for Installation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<16} {}", self.version, self.binary.display())
+    }
+}
+
+/// Registry of the StarCCM+ installations
+///
+/// The registry is made of the installations listed in the configuration (`starccm.installations`)
+/// and of the installations found in the directories `starccm.search` as `<dir>/*/STAR-CCM+*/star/bin/starccm+`
+#[derive(Debug, Clone, Default)]
+pub struct Installations(Vec<Installation>);
+impl Installations {
+    /// Builds the registry from the configuration
+    pub fn new(config: &Config) -> Self {
+        let mut installations: Vec<_> = config
+            .starccm
+            .installations
+            .iter()
+            .map(|(version, binary)| Installation {
+                version: version.clone(),
+                binary: binary.clone(),
+            })
+            .collect();
+        for dir in config.starccm.search.iter() {
+            for installation in scan(dir) {
+                if !installations
+                    .iter()
+                    .any(|i| i.version == installation.version)
+                {
+                    installations.push(installation);
+                }
+            }
+        }
+        installations.sort_by(|a, b| {
+            version_key(&a.version)
+                .cmp(&version_key(&b.version))
+                .then_with(|| a.version.cmp(&b.version))
+        });
+        Self(installations)
+    }
+    /// Returns the installation of the given version
+    pub fn find(&self, version: &str) -> Option<&Installation> {
+        self.0.iter().find(|i| i.version == version)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Installation> {
+        self.0.iter()
+    }
+}
+
+/// Numeric components of a version, so that `2.10` sorts after `2.9` and `19.02` after `2.10`
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse().ok())
+        .collect()
+}
+
+/// Scans `dir` for `*/STAR-CCM+*/star/bin/starccm+`
+fn scan(dir: &Path) -> Vec<Installation> {
+    let mut installations = vec![];
+    let Ok(entries) = fs::read_dir(dir) else {
+        return installations;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Ok(sub_entries) = fs::read_dir(entry.path()) else {
+            continue;
+        };
+        for sub_entry in sub_entries.filter_map(|e| e.ok()) {
+            let name = sub_entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with("STAR-CCM+") {
+                continue;
+            }
+            let binary = sub_entry.path().join("star").join("bin").join("starccm+");
+            let version = parse_version(&name)
+                .or_else(|| parse_version(&entry.file_name().to_string_lossy()));
+            if let Some(version) = version
+                && binary.is_file()
+            {
+                installations.push(Installation { version, binary });
+            }
+        }
+    }
+    installations
+}
+
+/// Number of bytes of the header of a sim searched for the StarCCM+ version
+const SIM_HEADER: u64 = 1 << 20;
+
+/// StarCCM+ version a sim was saved with, read from the `STAR-CCM+ <version>` mark of its header
+///
+/// Returns `None` if the header has no such mark
+pub fn sim_version(sim: &Path) -> io::Result<Option<String>> {
+    let mut header = vec![];
+    File::open(sim)?.take(SIM_HEADER).read_to_end(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+    Ok(header.match_indices("STAR-CCM+").find_map(|(i, mark)| {
+        let rest = &header[i + mark.len()..];
+        let end = rest
+            .char_indices()
+            .nth(24)
+            .map_or(rest.len(), |(end, _)| end);
+        parse_version(&rest[..end])
+    }))
+}
+
+/// StarCCM+ version of a binary, read from its installation path
+pub fn binary_version(binary: &Path) -> Option<String> {
+    binary
+        .ancestors()
+        .filter_map(|p| p.file_name())
+        .find_map(|name| parse_version(&name.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted_by_version_number() {
+        let mut config = Config::default();
+        config.starccm.search.clear();
+        for version in ["19.02.009", "2.10.001", "18.04.008", "2.9.003"] {
+            config.starccm.installations.insert(
+                version.to_string(),
+                PathBuf::from(format!("/opt/{version}/starccm+")),
+            );
+        }
+        let installations = Installations::new(&config);
+        let versions: Vec<_> = installations.iter().map(|i| i.version.as_str()).collect();
+        assert_eq!(versions, ["2.9.003", "2.10.001", "18.04.008", "19.02.009"]);
+    }
+}
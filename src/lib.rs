@@ -1,32 +1,66 @@
-use std::{env, sync::LazyLock};
-
 use anyhow::Result;
 use quick_xml::{Reader, events::Event};
 
+mod artifact;
+mod bundled;
+mod cache;
+mod campaign;
+mod cancel;
 mod checklist;
+mod config;
 mod coordinate_systems;
+mod doctor;
 mod expectation;
+mod installations;
+mod license;
+mod macro_result;
+mod outcome;
+mod pipeline;
 mod play_macro;
+mod pool;
+mod results;
+mod retry;
+mod scheduler;
+mod selection;
+mod template;
 mod test_properties;
+#[cfg(test)]
+mod testing;
+mod timestamp;
 mod wind_speed;
+pub use artifact::{Artifact, ArtifactError, collect_artifacts};
+pub use bundled::{BUNDLED_MACROS, BundledError, BundledMacro, MacroStatus};
+pub use cache::{CacheEntry, CacheError, ReportCache, SimIdentity, cache_dir, content_hash};
+pub use campaign::{Campaign, CampaignError, CaseMatrix, CaseStatus, Progress};
+pub use cancel::{ProcessGroup, cancel, handle_interrupts, is_cancelled, kill_group};
 pub use checklist::{Check, CheckList};
+pub use config::{
+    CacheConfig, CheckConfig, Config, ConfigError, Layout, LicenseConfig, LicenseMode,
+    MacrosConfig, OutputConfig, Parallel, PodkeyVia, SchedulerConfig, Source, StarccmConfig,
+    config, config_dir, desktop_dir, home_dir,
+};
 pub use coordinate_systems::{check_tcs, check_tcs0};
+pub use doctor::{Diagnostic, Health, diagnose, parse_version, starccm_version};
 pub use expectation::Expectation;
-pub use play_macro::Macro;
+pub use installations::{Installation, Installations, binary_version, sim_version};
+pub use license::{DEFERRED_PODKEY, License, LicenseError, Secret};
+pub use macro_result::{MacroResult, RESULT_TAG, ResultError};
+pub use outcome::{
+    FailureKind, LogAnalyzer, MacroOutcome, OutcomeError, Pattern, PatternConfig,
+    ZERO_EXIT_FAILURES,
+};
+pub use pipeline::{Action, OnFailure, Pipeline, PipelineError, Step, StepStatus};
+pub use play_macro::{Macro, MacroError};
+pub use pool::{catch_panic, run_concurrently, workers};
+pub use results::{CaseFolder, Recheck, Verdict};
+pub use retry::RetryPolicy;
+pub use scheduler::{Job, JobStatus, Local, Pbs, Scheduler, SchedulerError, SchedulerKind, Slurm};
+pub use selection::{SelectionError, SimFilter};
+pub use template::{PLACEHOLDERS, Template, TemplateError, Variables};
 pub use test_properties::TestProperty;
+pub use timestamp::{now, timestamp};
 pub use wind_speed::{WindSpeed, WindSpeedError};
 
-/// Path to the STARCCM+ binary
-pub static STARCCM: LazyLock<String> = LazyLock::new(|| {
-    let starcmm = env::var("STARCCM")
-        .unwrap_or("/opt/Siemens/17.06.007/STAR-CCM+17.06.007/star/bin/starccm+".to_string());
-    println!("Using: {starcmm}");
-    starcmm
-});
-/// Path to the STARCCM+ java macro
-pub static STARCCM_MACROS: LazyLock<String> =
-    LazyLock::new(|| env::var("STARCCM_MACROS").unwrap_or("/home/ubuntu/Desktop/".to_string()));
-
 #[derive(Debug, thiserror::Error)]
 pub enum CfdCheckListError {
     #[error("wrong CFD setting for ({0})")]
@@ -73,13 +107,13 @@ pub fn match_report_to_case(file: &str, case: &str) -> Result<(), CfdCheckListEr
     loop {
         match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"SummaryReport" {
-                    if let Ok(Some(attr)) = e.try_get_attribute("Name") {
-                        if attr.value.as_ref() == case.to_string().into_bytes() {
-                            break Ok(());
-                        } else {
-                            break Err(CfdCheckListError::Mismatch(case.to_string()));
-                        }
+                if e.name().as_ref() == b"SummaryReport"
+                    && let Ok(Some(attr)) = e.try_get_attribute("Name")
+                {
+                    if attr.value.as_ref() == case.to_string().into_bytes() {
+                        break Ok(());
+                    } else {
+                        break Err(CfdCheckListError::Mismatch(case.to_string()));
                     }
                 }
             }
@@ -98,35 +132,46 @@ pub struct Case {
     zen: u32,
     az: u32,
     config: String,
-    // wind_speed: u32,
+    wind_speed: Option<u32>,
 }
 impl Case {
     pub fn new(name: &str) -> Self {
+        Self::parse(name).unwrap_or_else(|| panic!("invalid case name: {name}"))
+    }
+    /// Parses a case name like `zen30az000_OS_7ms`, returning `None` if the name does not match
+    pub fn parse(name: &str) -> Option<Self> {
         let mut case_parts = name.split("_");
-        let mut zen_az = case_parts.next().unwrap().split("az");
-        let zen = zen_az
+        let mut zen_az = case_parts.next()?.split("az");
+        let zen = zen_az.next()?.strip_prefix("zen")?.parse::<u32>().ok()?;
+        let az = zen_az.last()?.parse::<u32>().ok()?;
+        let config = case_parts.next()?.to_string();
+        let wind_speed = case_parts
             .next()
-            .unwrap()
-            .strip_prefix("zen")
-            .unwrap()
-            .parse::<u32>()
-            .unwrap();
-        let az = zen_az.last().unwrap().parse::<u32>().unwrap();
-        let config = case_parts.next().unwrap().to_string();
-        // let wind_speed = case_parts
-        //     .next()
-        //     .unwrap()
-        //     .strip_suffix("ms")
-        //     .unwrap()
-        //     .parse::<u32>()
-        //     .unwrap();
-        Self {
+            .and_then(|part| part.strip_suffix("ms"))
+            .and_then(|wind_speed| wind_speed.parse::<u32>().ok());
+        Some(Self {
             // name: name.to_string(),
             zen,
             az,
             config,
-            // wind_speed,
-        }
+            wind_speed,
+        })
+    }
+    /// Zenith angle in degrees
+    pub fn zen(&self) -> u32 {
+        self.zen
+    }
+    /// Azimuth angle in degrees
+    pub fn az(&self) -> u32 {
+        self.az
+    }
+    /// Telescope configuration, e.g. `OS`
+    pub fn config(&self) -> &str {
+        &self.config
+    }
+    /// Wind speed in m/s, if the case name ends with it
+    pub fn wind_speed(&self) -> Option<u32> {
+        self.wind_speed
     }
     pub fn parts(&self) -> Vec<String> {
         vec![
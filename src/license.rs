@@ -0,0 +1,329 @@
+use std::{
+    env,
+    fmt::{self, Debug, Display},
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use crate::{LicenseConfig, LicenseMode, PodkeyVia};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LicenseError {
+    #[error(
+        "missing power-on-demand key (set license.podkey_file, license.podkey_command or the PODKEY environment variable)"
+    )]
+    Missing,
+    #[error("failed to read the power-on-demand key file {0:?}")]
+    Read(PathBuf, #[source] io::Error),
+    #[error(
+        "the power-on-demand key file {0:?} is accessible by other users (mode {1:o}), restrict it with `chmod 600`"
+    )]
+    Permissions(PathBuf, u32),
+    #[error("failed to run the power-on-demand key command `{0}`")]
+    Command(String, #[source] io::Error),
+    #[error("the power-on-demand key command `{0}` failed ({1})")]
+    CommandStatus(String, ExitStatus),
+    #[error("the power-on-demand key from {0} is empty")]
+    Empty(String),
+}
+
+/// Key shown in place of the output of `license.podkey_command` by [License::deferred]
+pub const DEFERRED_PODKEY: &str = "<podkey_command>";
+
+/// A secret value that is never displayed
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+    /// The secret value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+    /// Replaces the secret in `text` with `********`
+    pub fn redact(&self, text: &str) -> String {
+        if self.0.is_empty() || self.0 == DEFERRED_PODKEY {
+            text.to_string()
+        } else {
+            text.replace(&self.0, "********")
+        }
+    }
+}
+impl Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(********)")
+    }
+}
+impl Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "********")
+    }
+}
+
+/// StarCCM+ license
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum License {
+    /// Power-on-demand license checked out from the license servers
+    PowerOnDemand {
+        podkey: Secret,
+        servers: Vec<String>,
+        via: PodkeyVia,
+    },
+    /// License checked out from the site license servers, the first server being tried first
+    Server { servers: Vec<String> },
+    /// No license, for tools that do not need one
+    None,
+}
+impl License {
+    /// Builds the license from the configuration
+    ///
+    /// The power-on-demand key is read from, in order of precedence, the output of `license.podkey_command`,
+    /// the file `license.podkey_file` or the `PODKEY` environment variable
+    pub fn new(config: &LicenseConfig) -> Result<Self, LicenseError> {
+        Self::build(config, true)
+    }
+    /// Builds the license from the configuration without running `license.podkey_command`,
+    /// whose key is replaced with [DEFERRED_PODKEY]
+    pub fn deferred(config: &LicenseConfig) -> Result<Self, LicenseError> {
+        Self::build(config, false)
+    }
+    fn build(config: &LicenseConfig, run_command: bool) -> Result<Self, LicenseError> {
+        let servers: Vec<_> = Some(config.server.clone())
+            .into_iter()
+            .chain(config.fallbacks.iter().cloned())
+            .collect();
+        Ok(match config.mode {
+            LicenseMode::Pod => License::PowerOnDemand {
+                podkey: podkey(config, run_command)?,
+                servers,
+                via: config.podkey_via,
+            },
+            LicenseMode::Server => License::Server { servers },
+            LicenseMode::None => License::None,
+        })
+    }
+    /// License servers, as the value of the StarCCM+ `-licpath` option
+    pub fn licpath(&self) -> Option<String> {
+        match self {
+            License::PowerOnDemand { servers, .. } | License::Server { servers } => {
+                Some(servers.join(":"))
+            }
+            License::None => None,
+        }
+    }
+    /// StarCCM+ command line arguments
+    ///
+    /// The arguments include the power-on-demand key if it is passed with `-podkey`, so they must not be printed
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let License::PowerOnDemand { podkey, via, .. } = self {
+            args.push("-power".to_string());
+            if *via == PodkeyVia::Args {
+                args.extend(["-podkey".to_string(), podkey.expose().to_string()]);
+            }
+        }
+        if let Some(licpath) = self.licpath() {
+            args.extend(["-licpath".to_string(), licpath]);
+        }
+        args
+    }
+    /// StarCCM+ environment variables
+    pub fn envs(&self) -> Vec<(&'static str, &str)> {
+        match self {
+            License::PowerOnDemand {
+                podkey,
+                via: PodkeyVia::Env,
+                ..
+            } => vec![("LM_PROJECT", podkey.expose())],
+            _ => vec![],
+        }
+    }
+    /// Removes the license secrets from `text`
+    pub fn redact(&self, text: &str) -> String {
+        match self {
+            License::PowerOnDemand { podkey, .. } => podkey.redact(text),
+            _ => text.to_string(),
+        }
+    }
+}
+impl Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            License::PowerOnDemand {
+                podkey, servers, ..
+            } => write!(
+                f,
+                "power-on-demand (podkey: {podkey}) from {}",
+                servers.join(", ")
+            ),
+            License::Server { servers } => write!(f, "license servers {}", servers.join(", ")),
+            License::None => write!(f, "no license"),
+        }
+    }
+}
+
+/// Reads the power-on-demand key, running `license.podkey_command` only if `run_command` is set
+fn podkey(config: &LicenseConfig, run_command: bool) -> Result<Secret, LicenseError> {
+    let (key, source) = if let Some(command) = config.podkey_command.as_ref() {
+        if !run_command {
+            return Ok(Secret::new(DEFERRED_PODKEY));
+        }
+        (podkey_command(command)?, format!("`{command}`"))
+    } else if let Some(path) = config.podkey_file.as_ref() {
+        (podkey_file(path)?, format!("{path:?}"))
+    } else {
+        (
+            env::var("PODKEY").map_err(|_| LicenseError::Missing)?,
+            "PODKEY".to_string(),
+        )
+    };
+    let key = key.trim();
+    if key.is_empty() {
+        Err(LicenseError::Empty(source))
+    } else {
+        Ok(Secret::new(key))
+    }
+}
+
+/// Reads the power-on-demand key from a file that only its owner can access
+fn podkey_file(path: &Path) -> Result<String, LicenseError> {
+    let metadata = fs::metadata(path).map_err(|e| LicenseError::Read(path.to_path_buf(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Err(LicenseError::Permissions(path.to_path_buf(), mode));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    fs::read_to_string(path).map_err(|e| LicenseError::Read(path.to_path_buf(), e))
+}
+
+/// Reads the power-on-demand key from the standard output of a shell command
+fn podkey_command(command: &str) -> Result<String, LicenseError> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| LicenseError::Command(command.to_string(), e))?;
+    if !output.status.success() {
+        return Err(LicenseError::CommandStatus(
+            command.to_string(),
+            output.status,
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const KEY: &str = "Xk93-secret-podkey";
+
+    fn pod(via: PodkeyVia) -> License {
+        License::PowerOnDemand {
+            podkey: Secret::new(KEY),
+            servers: vec!["1999@flex".to_string(), "1999@backup".to_string()],
+            via,
+        }
+    }
+
+    #[test]
+    fn key_passed_with_the_environment() {
+        let license = pod(PodkeyVia::Env);
+        assert_eq!(
+            license.args(),
+            ["-power", "-licpath", "1999@flex:1999@backup"]
+        );
+        assert_eq!(license.envs(), [("LM_PROJECT", KEY)]);
+        let license = pod(PodkeyVia::Args);
+        assert_eq!(
+            license.args(),
+            [
+                "-power",
+                "-podkey",
+                KEY,
+                "-licpath",
+                "1999@flex:1999@backup"
+            ]
+        );
+        assert!(license.envs().is_empty());
+    }
+
+    #[test]
+    fn key_redacted() {
+        let license = pod(PodkeyVia::Env);
+        for text in [license.to_string(), format!("{license:?}")] {
+            assert!(!text.contains(KEY), "{text}");
+        }
+        assert_eq!(
+            license.redact(&format!("LM_PROJECT={KEY} -podkey {KEY}")),
+            "LM_PROJECT=******** -podkey ********"
+        );
+        // the placeholder of a deferred key is shown as is
+        assert_eq!(
+            Secret::new(DEFERRED_PODKEY).redact("LM_PROJECT=<podkey_command>"),
+            "LM_PROJECT=<podkey_command>"
+        );
+    }
+
+    fn config(file: Option<PathBuf>, command: Option<&str>) -> LicenseConfig {
+        LicenseConfig {
+            podkey_file: file,
+            podkey_command: command.map(str::to_string),
+            ..LicenseConfig::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn key_file_readable_by_its_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("license_file");
+        let path = dir.join("podkey");
+        fs::write(&path, format!("{KEY}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(matches!(
+            License::new(&config(Some(path.clone()), None)),
+            Err(LicenseError::Permissions(_, 0o644))
+        ));
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(
+            License::new(&config(Some(path), None)).unwrap().envs(),
+            [("LM_PROJECT", KEY)]
+        );
+    }
+
+    #[test]
+    fn key_command() {
+        let config = config(None, Some(&format!("echo {KEY}")));
+        assert_eq!(License::new(&config).unwrap().envs(), [("LM_PROJECT", KEY)]);
+        assert_eq!(
+            License::deferred(&config).unwrap().envs(),
+            [("LM_PROJECT", DEFERRED_PODKEY)]
+        );
+        let config = LicenseConfig {
+            podkey_command: Some("exit 3".to_string()),
+            ..config
+        };
+        assert!(matches!(
+            License::new(&config),
+            Err(LicenseError::CommandStatus(..))
+        ));
+        assert!(matches!(
+            License::new(&LicenseConfig {
+                podkey_command: Some("true".to_string()),
+                ..config
+            }),
+            Err(LicenseError::Empty(_))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Prefix of the StarCCM+ output lines carrying a JSON object returned by the macro
+pub const RESULT_TAG: &str = "@@cfdsim";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResultError {
+    #[error("invalid macro result {line:?}")]
+    Json {
+        line: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("invalid macro result {line:?}: expected a JSON object")]
+    Object { line: String },
+    #[error("invalid value of {key:?} in the macro result {line:?}")]
+    Value {
+        key: String,
+        line: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Data returned by a macro with `@@cfdsim {...}` lines
+///
+/// The keys `cells`, `physical_time` and `files` (a path or a list of paths) are parsed into their fields,
+/// the other keys are kept in `values`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MacroResult {
+    /// Number of cells of the mesh
+    pub cells: Option<u64>,
+    /// Physical time reached by the solver, in seconds
+    pub physical_time: Option<f64>,
+    /// Files written by the macro
+    pub files: Vec<PathBuf>,
+    /// Values returned by custom macros
+    pub values: BTreeMap<String, Value>,
+}
+impl MacroResult {
+    /// Parses the tagged lines of the StarCCM+ output
+    ///
+    /// The values of a key given on several lines are overridden by the last one, except for the files that are accumulated
+    pub fn parse(output: &str) -> Result<Self, ResultError> {
+        let mut result = Self::default();
+        for line in output.lines() {
+            let Some(json) = line
+                .trim()
+                .strip_prefix(RESULT_TAG)
+                .filter(|json| json.starts_with(char::is_whitespace))
+            else {
+                continue;
+            };
+            let object: Map<String, Value> = match serde_json::from_str(json) {
+                Ok(Value::Object(object)) => object,
+                Ok(_) => {
+                    return Err(ResultError::Object {
+                        line: line.to_string(),
+                    });
+                }
+                Err(source) => {
+                    return Err(ResultError::Json {
+                        line: line.to_string(),
+                        source,
+                    });
+                }
+            };
+            for (key, value) in object {
+                let invalid = |source| ResultError::Value {
+                    key: key.clone(),
+                    line: line.to_string(),
+                    source,
+                };
+                match key.as_str() {
+                    "cells" => result.cells = serde_json::from_value(value).map_err(invalid)?,
+                    "physical_time" => {
+                        result.physical_time = serde_json::from_value(value).map_err(invalid)?
+                    }
+                    "files" => match value {
+                        Value::Array(_) => result.files.extend(
+                            serde_json::from_value::<Vec<PathBuf>>(value).map_err(invalid)?,
+                        ),
+                        value => result
+                            .files
+                            .push(serde_json::from_value(value).map_err(invalid)?),
+                    },
+                    _ => {
+                        result.values.insert(key, value);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+    /// Checks if the macro has not returned anything
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+impl Display for MacroResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items = vec![];
+        if let Some(cells) = self.cells {
+            items.push(format!("{cells} cells"));
+        }
+        if let Some(time) = self.physical_time {
+            items.push(format!("physical time {time}s"));
+        }
+        if !self.files.is_empty() {
+            items.push(format!(
+                "files {}",
+                self.files
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        items.extend(
+            self.values
+                .iter()
+                .map(|(key, value)| format!("{key}={value}")),
+        );
+        write!(f, "{}", items.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_value_overrides_and_files_accumulate() {
+        let output = r#"Loading the sim
+@@cfdsim {"cells": 1000, "files": "/tmp/report.xml", "drag": 1.5}
+  @@cfdsim {"cells": 2000, "physical_time": 900.0, "files": ["/tmp/a.png", "/tmp/b.png"]}
+@@cfdsim {"drag": 2.5}
+"#;
+        let result = MacroResult::parse(output).unwrap();
+        assert_eq!(result.cells, Some(2000));
+        assert_eq!(result.physical_time, Some(900.));
+        assert_eq!(
+            result.files,
+            [
+                PathBuf::from("/tmp/report.xml"),
+                PathBuf::from("/tmp/a.png"),
+                PathBuf::from("/tmp/b.png")
+            ]
+        );
+        assert_eq!(result.values["drag"], 2.5);
+    }
+
+    #[test]
+    fn untagged_output_is_empty() {
+        let result = MacroResult::parse("@@cfdsimfoo {\n@@cfdsim\nend\n").unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn escaped_paths() {
+        let result =
+            MacroResult::parse(r#"@@cfdsim {"files": ["C:\\sims\\a \"b\".sim"]}"#).unwrap();
+        assert_eq!(result.files, [PathBuf::from(r#"C:\sims\a "b".sim"#)]);
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert!(matches!(
+            MacroResult::parse("@@cfdsim {\"files\": [\"/tmp/a\"}"),
+            Err(ResultError::Json { .. })
+        ));
+        assert!(matches!(
+            MacroResult::parse("@@cfdsim [1, 2]"),
+            Err(ResultError::Object { .. })
+        ));
+        assert!(matches!(
+            MacroResult::parse("@@cfdsim {\"cells\": \"many\"}"),
+            Err(ResultError::Value { key, .. }) if key == "cells"
+        ));
+    }
+}
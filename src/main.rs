@@ -1,21 +1,35 @@
-use clap::{Parser, Subcommand};
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
 use std::{
+    collections::BTreeMap,
+    env,
+    fmt::{self, Display},
     fs::{self, File},
     io::Write,
     path::{Path, PathBuf},
+    process,
+    sync::{Mutex, MutexGuard, PoisonError},
     thread,
+    time::{Duration, Instant},
 };
 
 use cfdsim::{
-    Case, CheckList, Macro, STARCCM_MACROS, TestProperty, Tests, WindSpeed, check_tcs, check_tcs0,
-    match_report_to_case,
+    Action, Artifact, BUNDLED_MACROS, BundledMacro, Campaign, Case, CaseFolder, CheckList, Config,
+    Health, Installations, Layout, License, LicenseMode, Macro, MacroError, MacroResult,
+    MacroStatus, OutputConfig, Parallel, Pipeline, Recheck, ReportCache, SchedulerKind, SimFilter,
+    StepStatus, Template, TestProperty, Tests, Variables, WindSpeed, catch_panic, check_tcs,
+    check_tcs0, config, diagnose, handle_interrupts, match_report_to_case, now, run_concurrently,
+    workers,
 };
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     /// Full path to a CFD sim file
-    case: String,
+    case: Option<String>,
+    /// Path to a configuration file overriding the system, user and project configuration files
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -27,408 +41,1587 @@ enum Commands {
         /// Path to the CFD summary XML report
         #[arg(short, long)]
         report: Option<String>,
-        /// Write checklist report to folder
-        #[arg(short, long)]
+        /// Write checklist report to folder [config: check.folder]
+        #[arg(short, long, overrides_with = "no_folder")]
         folder: bool,
-        /// skipping the generation of the scenes views
-        #[arg(long)]
+        /// Does not write the checklist report to a folder [config: check.folder]
+        #[arg(long, overrides_with = "folder")]
+        no_folder: bool,
+        /// Generates the scenes views [config: check.scenes]
+        #[arg(long, overrides_with = "no_scenes")]
+        scenes: bool,
+        /// skipping the generation of the scenes views [config: check.scenes]
+        #[arg(long, overrides_with = "scenes")]
         no_scenes: bool,
+        /// Number of sims checked concurrently if the case is a directory [config: check.jobs]
+        ///
+        /// The StarCCM+ runs of customized macros without placeholders are done one at a time
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Regenerates the report even if a cached report is available
+        #[arg(long)]
+        refresh: bool,
+        /// Compares the content hash of the sim to the cached report hash [config: check.hash]
+        #[arg(long, overrides_with = "no_hash")]
+        hash: bool,
+        /// Trusts the cached report without comparing the content hash of the sim [config: check.hash]
+        #[arg(long, overrides_with = "hash")]
+        no_hash: bool,
+        /// Re-checks the sims with an existing checklist folder, archiving the previous result [config: check.recheck]
+        #[arg(long, value_enum)]
+        recheck: Option<Recheck>,
+        /// Root directory of the checklist folders [config: output.root]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Directory where StarCCM+ writes the scenes hardcopies [config: output.scenes]
+        #[arg(long)]
+        scenes_source: Option<PathBuf>,
+        /// Layout of the checklist folders in the root directory [config: output.layout]
+        #[arg(long, value_enum)]
+        layout: Option<Layout>,
+        #[command(flatten)]
+        select: SelectArgs,
+        #[command(flatten)]
+        run: RunArgs,
     },
     /// Executes a java macro
     PlayMacro {
         /// Full path to the java macro
         java: String,
-        /// Applies the macro in parallel (optionally passing the batch size)
+        /// Applies the macro in parallel to the sims of a directory (optionally passing the number of concurrent runs, by default the number of CPUs)
         #[arg(short, long, require_equals = true)]
         batch: Option<Option<usize>>,
+        /// Submits the macros to a job scheduler [config: scheduler.kind]
+        #[arg(long, value_enum)]
+        scheduler: Option<SchedulerKind>,
+        /// Applies the macro to the sims of the sub-directories too
+        #[arg(short = 'R', long)]
+        recursive: bool,
+        #[command(flatten)]
+        select: SelectArgs,
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Runs the steps of a pipeline on the sims
+    RunPipeline {
+        /// Pipeline TOML file
+        pipeline: PathBuf,
+        /// Number of sims processed concurrently if the case is a directory
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Writes the checklist reports of the check steps to folders [config: check.folder]
+        #[arg(short, long, overrides_with = "no_folder")]
+        folder: bool,
+        /// Does not write the checklist reports of the check steps to folders [config: check.folder]
+        #[arg(long, overrides_with = "folder")]
+        no_folder: bool,
+        /// Runs the pipeline on the sims of the cases of a campaign instead of the case
+        #[arg(long)]
+        campaign: Option<PathBuf>,
+        #[command(flatten)]
+        select: SelectArgs,
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Plays a java macro on the cases of a campaign and collects the artifacts
+    Batch {
+        /// Campaign TOML file
+        campaign: PathBuf,
+        /// Number of concurrent StarCCM+ runs
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+        /// Runs the cases whose artifacts already exist in the destination directory
+        #[arg(long)]
+        force: bool,
+        /// Submits the macros to a job scheduler [config: scheduler.kind]
+        #[arg(long, value_enum)]
+        scheduler: Option<SchedulerKind>,
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Reports the status of the cases of a campaign
+    Status {
+        /// Campaign TOML file
+        campaign: PathBuf,
+    },
+    /// Lists the StarCCM+ installations
+    Installations,
+    /// Manages the cache of the CFD summary XML reports
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Inspects the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Checks the environment the CLI relies on
+    Doctor,
+    /// Manages the java macros bundled with the CLI
+    Macros {
+        #[command(subcommand)]
+        command: MacrosCommands,
     },
 }
 
-fn checklist(
-    case_path: &Path,
+#[derive(Subcommand)]
+enum MacrosCommands {
+    /// Lists the bundled macros and the status of their copies in the macros directory
+    List,
+    /// Writes the bundled macros into the macros directory to be customized
+    Export {
+        /// Directory the macros are written to [config: macros.dir]
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Overwrites the customized macros
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Prints the effective configuration and where each value comes from
+    Show,
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Lists the cached reports
+    List,
+    /// Removes the reports of the sims that have changed or no longer exist
+    Prune {
+        /// Removes all the cached reports
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+/// StarCCM+ run options
+#[derive(Args, Clone)]
+struct RunArgs {
+    /// StarCCM+ version, overriding the version pinned in the configuration [config: starccm.version]
+    #[arg(long)]
+    starccm_version: Option<String>,
+    /// Opens the sims with the default StarCCM+ binary if the pinned version is not installed
+    #[arg(long)]
+    force_version: bool,
+    /// StarCCM+ license mode [config: license.mode]
+    #[arg(long, value_enum)]
+    license: Option<LicenseMode>,
+    /// Prefixes the StarCCM+ output lines with the case name
+    #[arg(long)]
+    prefix: bool,
+    /// Time after which StarCCM+ is killed, in seconds [config: starccm.timeout]
+    #[arg(long)]
+    timeout: Option<u64>,
+    /// Parallel execution profile [config: profiles.<PROFILE>]
+    #[arg(long)]
+    profile: Option<String>,
+    /// Number of StarCCM+ processes
+    #[arg(long)]
+    np: Option<usize>,
+    /// MPI driver
+    #[arg(long)]
+    mpi: Option<String>,
+    /// File with the hosts of the StarCCM+ processes
+    #[arg(long)]
+    machinefile: Option<PathBuf>,
+    /// Extra StarCCM+ argument, repeated for each argument
+    #[arg(long = "starccm-arg", allow_hyphen_values = true)]
+    starccm_args: Vec<String>,
+    /// Prints the StarCCM+ command lines and the output locations without running anything
+    #[arg(long)]
+    dry_run: bool,
+}
+impl RunArgs {
+    /// Parallel execution settings of the profile overridden by the command line options
+    fn parallel(&self) -> anyhow::Result<Parallel> {
+        let profile = match self.profile.as_ref() {
+            Some(name) => config()
+                .profiles
+                .get(name)
+                .cloned()
+                .with_context(|| format!("no profile {name:?} in the configuration"))?,
+            None => Parallel::default(),
+        };
+        Ok(profile.merge(Parallel {
+            np: self.np,
+            mpi: self.mpi.clone(),
+            machinefile: self.machinefile.clone(),
+            args: self.starccm_args.clone(),
+        }))
+    }
+    /// Number of concurrent runs allowed for `jobs` requested runs,
+    /// given the number of processes of each run and the license seats
+    fn workers(&self, jobs: usize) -> anyhow::Result<usize> {
+        let np = self.parallel()?.np.unwrap_or(1);
+        Ok(workers(jobs, np, config().license.seats))
+    }
+    /// Creates a [Macro] with the run options
+    fn new_macro(&self, case_path: &Path, java: impl Into<PathBuf>) -> anyhow::Result<Macro> {
+        let mut r#macro = Macro::new(case_path, java)?
+            .version(self.starccm_version.clone())
+            .force_version(self.force_version)
+            .parallel(self.parallel()?)
+            .prefix(self.prefix);
+        if let Some(timeout) = self.timeout {
+            r#macro = r#macro.timeout(Some(Duration::from_secs(timeout)));
+        }
+        if let Some(mode) = self.license {
+            let mut license = config().license.clone();
+            license.mode = mode;
+            r#macro = r#macro.license(if self.dry_run {
+                License::deferred(&license)?
+            } else {
+                License::new(&license)?
+            });
+        }
+        Ok(r#macro)
+    }
+}
+
+/// Selection of the sims of a directory
+#[derive(Args, Clone)]
+struct SelectArgs {
+    /// Selects the files matching the glob pattern (`*.sim` by default), repeated for each pattern
+    #[arg(long)]
+    include: Vec<String>,
+    /// Skips the files matching the glob pattern, repeated for each pattern
+    #[arg(long)]
+    exclude: Vec<String>,
+    /// Selects the files matching the regular expression
+    #[arg(long)]
+    include_regex: Vec<String>,
+    /// Skips the files matching the regular expression
+    #[arg(long)]
+    exclude_regex: Vec<String>,
+    /// Selects the cases with one of the zenith angles, e.g. `--zen 30,60`
+    #[arg(long, value_delimiter = ',')]
+    zen: Vec<u32>,
+    /// Selects the cases with one of the azimuth angles, e.g. `--az 0,90`
+    #[arg(long, value_delimiter = ',')]
+    az: Vec<u32>,
+    /// Selects the cases with one of the telescope configurations, e.g. `--case-config OS`
+    #[arg(long, value_delimiter = ',')]
+    case_config: Vec<String>,
+    /// Prints the selected sims without processing them
+    #[arg(long)]
+    list: bool,
+}
+impl SelectArgs {
+    fn filter(&self) -> anyhow::Result<SimFilter> {
+        let mut filter = SimFilter::new()
+            .zen(self.zen.iter().copied())
+            .az(self.az.iter().copied())
+            .config(self.case_config.iter().cloned());
+        for pattern in &self.include {
+            filter = filter.include(pattern)?;
+        }
+        for pattern in &self.exclude {
+            filter = filter.exclude(pattern)?;
+        }
+        for regex in &self.include_regex {
+            filter = filter.include_regex(regex)?;
+        }
+        for regex in &self.exclude_regex {
+            filter = filter.exclude_regex(regex)?;
+        }
+        Ok(filter)
+    }
+}
+
+/// Checklist options shared by all the sims
+struct CheckOptions<'a> {
     folder: bool,
-    report: Option<&str>,
+    report: Option<&'a str>,
     no_scenes: bool,
+    refresh: bool,
+    hash: bool,
+    recheck: Recheck,
+    cache: ReportCache,
+    outputs: OutputConfig,
+    filter: SimFilter,
+    list: bool,
+    run: RunArgs,
+    report_macro: CheckMacro,
+    scenes_macro: CheckMacro,
+}
+
+/// Java macro of the check command
+struct CheckMacro {
+    path: PathBuf,
+    /// the macro writes its outputs to `{{output_dir}}`, otherwise to fixed locations
+    template: bool,
+}
+impl CheckMacro {
+    /// Selects the customized copy of a bundled macro in the macros directory or the bundled macro
+    fn new(bundled: BundledMacro) -> anyhow::Result<Self> {
+        let dir = &config().macros.dir;
+        if bundled.status(dir)? == MacroStatus::Customized {
+            println!("using the customized macro {:?}", dir.join(bundled.name));
+        }
+        let path = bundled.path(dir)?;
+        Ok(Self {
+            template: Template::read(&path)?.is_some(),
+            path,
+        })
+    }
+    /// Directory of the macro outputs: `output_dir` for a template, `fixed` otherwise
+    fn outputs<'a>(&self, output_dir: &'a Path, fixed: &'a Path) -> &'a Path {
+        if self.template { output_dir } else { fixed }
+    }
+    /// Prevents the runs of the macros writing to fixed locations from overlapping
+    fn lock(&self) -> Option<MutexGuard<'static, ()>> {
+        (!self.template).then(|| FIXED_OUTPUTS.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// Checklist status of a sim
+enum Status {
+    Pass,
+    Fail,
+    /// the sim was already checked
+    Skipped,
+    /// the case name does not match any wind speed
+    Unchecked,
+}
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "PASS"),
+            Status::Fail => write!(f, "FAIL"),
+            Status::Skipped => write!(f, "SKIPPED"),
+            Status::Unchecked => write!(f, "UNCHECKED"),
+        }
+    }
+}
+
+/// Customized `report.java` and `scenes_views.java` macros without placeholders write their outputs to fixed locations,
+/// so StarCCM+ runs of these macros cannot overlap
+static FIXED_OUTPUTS: Mutex<()> = Mutex::new(());
+
+fn case_name(case_path: &Path) -> String {
+    case_path
+        .with_extension("")
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Collects the sim files in `case_path` together with the root of their checklist folders
+fn collect_sims(
+    case_path: &Path,
     root: PathBuf,
+    layout: Layout,
+    filter: &SimFilter,
+    sims: &mut Vec<(PathBuf, PathBuf)>,
 ) -> anyhow::Result<()> {
     if case_path.is_dir() {
-        println!("Applying checklist to all sim files in {case_path:?}");
-        for entry in fs::read_dir(case_path)? {
-            let path = entry?.path();
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if ext != "sim" {
-                        continue;
+        let mut entries = fs::read_dir(case_path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for path in entries {
+            if path.is_file() && !filter.matches(&path) {
+                continue;
+            }
+            let root = match layout {
+                Layout::Nested => root.join(case_path.file_name().unwrap()),
+                Layout::Flat => root.clone(),
+            };
+            collect_sims(path.as_path(), root, layout, filter, sims)?;
+        }
+    } else {
+        sims.push((case_path.to_path_buf(), root));
+    }
+    Ok(())
+}
+
+fn checklist(
+    case_path: &Path,
+    options: &CheckOptions,
+    jobs: usize,
+    root: PathBuf,
+) -> anyhow::Result<()> {
+    if !case_path.is_dir() {
+        if options.run.dry_run {
+            return dry_run_check(case_path, options, root);
+        }
+        check_sim(case_path, options, root)?;
+        return Ok(());
+    }
+    let mut sims = vec![];
+    collect_sims(
+        case_path,
+        root,
+        options.outputs.layout,
+        &options.filter,
+        &mut sims,
+    )?;
+    if options.list {
+        for (sim, _) in &sims {
+            println!("{}", sim.display());
+        }
+        return Ok(());
+    }
+    if options.run.dry_run {
+        for (sim, root) in sims {
+            dry_run_check(&sim, options, root)?;
+        }
+        return Ok(());
+    }
+    println!("Applying checklist to all sim files in {case_path:?}");
+    let n_sim = sims.len();
+    let (mut n_pass, mut n_fail, mut n_skip, mut n_unchecked, mut n_error) = (0, 0, 0, 0, 0);
+    run_concurrently(
+        options.run.workers(jobs)?,
+        sims,
+        |(sim, root)| {
+            let case = case_name(&sim);
+            (case, catch_panic(|| check_sim(&sim, options, root)))
+        },
+        |(case, status)| {
+            let i = n_pass + n_fail + n_skip + n_unchecked + n_error + 1;
+            match status {
+                Ok(status) => {
+                    println!("[{i}/{n_sim}] {case}: {status}");
+                    match status {
+                        Status::Pass => n_pass += 1,
+                        Status::Fail => n_fail += 1,
+                        Status::Skipped => n_skip += 1,
+                        Status::Unchecked => n_unchecked += 1,
                     }
                 }
+                Err(e) => {
+                    println!("[{i}/{n_sim}] {case}: ERROR {e:#}");
+                    n_error += 1;
+                }
             }
-            checklist(
-                path.as_path(),
-                folder,
-                report,
-                no_scenes,
-                root.join(case_path.file_name().unwrap()),
-            )?;
+        },
+    );
+    println!(
+        "{n_sim} sims: {n_pass} PASS, {n_fail} FAIL, {n_skip} SKIPPED, {n_unchecked} UNCHECKED, {n_error} ERROR"
+    );
+    if n_error > 0 {
+        anyhow::bail!("failed to check {n_error} of {n_sim} sims");
+    }
+    Ok(())
+}
+
+/// Prints the StarCCM+ runs and the outputs of the check of a sim
+fn dry_run_check(case_path: &Path, options: &CheckOptions, root: PathBuf) -> anyhow::Result<()> {
+    let CheckOptions {
+        folder,
+        report,
+        no_scenes,
+        refresh,
+        hash,
+        ref cache,
+        ref outputs,
+        ref run,
+        ref report_macro,
+        ref scenes_macro,
+        ..
+    } = *options;
+    let case = case_name(case_path);
+    let output_dir = check_dir(&case);
+    match (report, cache.get(case_path, hash)?) {
+        (Some(report), _) => println!("{case}: report {report:?}"),
+        (None, Some(cached)) if !refresh => println!("{case}: cached report {cached:?}"),
+        _ => println!(
+            "{}",
+            run.new_macro(case_path, &report_macro.path)?
+                .log_dir(outputs.root.join("logs"))
+                .output_dir(&output_dir)
+                .dry_run()?
+        ),
+    }
+    if !no_scenes {
+        println!(
+            "{}",
+            run.new_macro(case_path, &scenes_macro.path)?
+                .log_dir(outputs.root.join("logs"))
+                .output_dir(&output_dir)
+                .dry_run()?
+        );
+        println!(
+            "  scenes:  {:?}",
+            scenes_macro.outputs(&output_dir, &outputs.scenes)
+        );
+    }
+    if folder {
+        println!("  folder:  {:?}", root.join(format!("{case}@<PASS|FAIL>")));
+    } else if !no_scenes {
+        println!("  output:  {root:?}");
+    }
+    Ok(())
+}
+
+/// Directory where the macro templates of the check of a case write their outputs
+fn check_dir(case: &str) -> PathBuf {
+    env::temp_dir()
+        .join("cfdsim")
+        .join(format!("{case}-check-{}", process::id()))
+}
+
+fn check_sim(case_path: &Path, options: &CheckOptions, root: PathBuf) -> anyhow::Result<Status> {
+    let CheckOptions {
+        folder,
+        report,
+        no_scenes,
+        refresh,
+        hash,
+        recheck,
+        ref cache,
+        ref outputs,
+        ref run,
+        ref report_macro,
+        ..
+    } = *options;
+    let case = case_name(case_path);
+    let output_dir = check_dir(&case);
+
+    let case_folder = CaseFolder::new(&root, &case);
+    if folder && let Some(verdict) = case_folder.verdict() {
+        if !recheck.applies(verdict) {
+            println!("found existing folder: {case}@{verdict}, skipping {case}");
+            return Ok(Status::Skipped);
         }
+        println!("found existing folder: {case}@{verdict}, re-checking {case}");
+    };
+
+    let report_path;
+    let report = if let Some(report) = report {
+        report
     } else {
-        let case = case_path
-            .with_extension("")
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned();
-
-        if folder {
-            if root.join(format!("{case}@PASS")).is_dir() {
-                println!("found existing folder: {case}@PASS, skipping {case}");
-                return Ok(());
+        report_path = match cache.get(case_path, hash)? {
+            Some(cached) if !refresh => {
+                println!("{case} report found in cache: {cached:?}");
+                cached
             }
-            if root.join(format!("{case}@FAIL")).is_dir() {
-                println!("found existing folder: {case}@FAIL, skipping {case}");
-                return Ok(());
+            _ => {
+                println!("Building report for {case} ...");
+                let _guard = report_macro.lock();
+                fs::create_dir_all(&output_dir)?;
+                run.new_macro(case_path, &report_macro.path)?
+                    .log_dir(outputs.root.join("logs"))
+                    .output_dir(&output_dir)
+                    .play()
+                    .with_context(|| format!("failed to build report for {case}"))?;
+                let cached = cache.insert(
+                    case_path,
+                    &report_macro
+                        .outputs(&output_dir, Path::new("/tmp"))
+                        .join("report.xml"),
+                    hash,
+                )?;
+                let _ = fs::remove_dir_all(&output_dir);
+                println!("{case} report saved in {cached:?}");
+                cached
             }
         };
+        report_path.to_str().unwrap()
+    };
+    match_report_to_case(report, &case)?;
 
-        let report = if let Some(report) = report.as_ref() {
-            report
-        } else {
-            println!("Building report for {case} ...");
-            Macro::new(case_path, Path::new(&*STARCCM_MACROS).join("report.java"))?
-                .play()
-                .expect(&format!("failed to build report for {case}"));
-            println!(r#"{case} report saved in "/tmp/report.xml""#);
-            // println!("{:#?}", output);
-            "/tmp/report.xml"
-        };
-        match_report_to_case(report, &case)?;
+    let Ok(wind_speed) = WindSpeed::new(&case) else {
+        eprintln!("failed to parse wind speed from {case}");
+        return Ok(Status::Unchecked);
+    };
+    let duration = wind_speed.duration();
+    let start = wind_speed.start();
+    let scene_start = wind_speed.scene_start();
+    let u_max = wind_speed.u_max();
 
-        let Ok(wind_speed) = WindSpeed::new(&case) else {
-            eprintln!("failed to parse wind speed from {case}");
-            return Ok(());
-        };
-        let duration = wind_speed.duration();
-        let start = wind_speed.start();
-        let scene_start = wind_speed.scene_start();
-        let u_max = wind_speed.u_max();
-
-        println!("CHECKING {}...", case.to_uppercase());
-
-        let tcs = check_tcs(report, &case)?;
-        let tcs0 = check_tcs0(report, &case)?;
-
-        let ducts = TestProperty::new(vec![("ducts", b"commonBoundary")], b"PartSurfaces", "")
-            .check_ducts(report)?;
-        let ws = TestProperty::new(vec![("ws", b"commonBoundary")], b"PartSurfaces", "")
-            .check_ws(report)?;
-        let instvol = TestProperty::new(vec![("instvol", b"commonBoundary")], b"PartSurfaces", "")
-            .check_instvol(report)?;
-        let stripped_case = Case::new(&case);
-        let parts = stripped_case.parts();
-        let parts_as_str = parts.iter().map(|x| x.as_str()).collect::<Vec<_>>();
-
-        let test_props = vec![
-            TestProperty::new(
-                vec![("Umax", b"commonUserFieldFunction")],
-                b"Definition",
-                u_max,
-            ),
-            TestProperty::new(
-                vec![(
-                    "Maximum Physical Time",
-                    b"commonPhysicalTimeStoppingCriterion",
-                )],
-                b"MaximumTime",
-                duration,
-            ),
-            TestProperty::new(
-                vec![("T_upwind", b"basereportSumReport")],
-                b"Representation",
-                "Volume Mesh",
-            ),
-            TestProperty::new(
-                vec![("RI_tel", b"visScene"), ("Scalar 1", b"visScalarDisplayer")],
-                b"Representation",
-                ["Volume Mesh", "Latest Surface/Volume"],
-            ),
-            TestProperty::new(
-                vec![("RI_tel", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"DeltaTime",
-                "0.2 s",
-            ),
-            TestProperty::new(
-                vec![("RI_tel", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"StartQuantity",
-                scene_start,
-            ),
-            TestProperty::new(
-                vec![
-                    ("RI_wind", b"visScene"),
-                    ("Scalar 1", b"visScalarDisplayer"),
-                ],
-                b"Representation",
-                ["Volume Mesh", "Latest Surface/Volume"],
-            ),
-            TestProperty::new(
-                vec![("RI_wind", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"DeltaTime",
-                "0.2 s",
-            ),
-            TestProperty::new(
-                vec![("RI_wind", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"StartQuantity",
-                scene_start,
-            ),
-            TestProperty::new(
-                vec![
-                    ("vort_tel", b"visScene"),
-                    ("Scalar 1", b"visScalarDisplayer"),
-                ],
-                b"Representation",
-                ["Volume Mesh", "Latest Surface/Volume"],
-            ),
-            TestProperty::new(
-                vec![("vort_tel", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"DeltaTime",
-                "0.2 s",
-            ),
-            TestProperty::new(
-                vec![("vort_tel", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"StartQuantity",
-                scene_start,
-            ),
-            TestProperty::new(
-                vec![
-                    ("vort_wind", b"visScene"),
-                    ("Scalar 1", b"visScalarDisplayer"),
-                ],
-                b"Representation",
-                ["Volume Mesh", "Latest Surface/Volume"],
-            ),
-            TestProperty::new(
-                vec![("vort_wind", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"DeltaTime",
-                "0.2 s",
-            ),
-            TestProperty::new(
-                vec![("vort_wind", b"visScene"), ("Update", b"visSceneUpdate")],
-                b"StartQuantity",
-                scene_start,
-            ),
-            TestProperty::new(
-                vec![("M1p", b"commonXyzInternalTable")],
-                b"Representation",
-                "Volume Mesh",
-            ),
-            TestProperty::new(
-                vec![
-                    ("M1p", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"DeltaTime",
-                "0.05 s",
-            ),
-            TestProperty::new(
-                vec![
-                    ("M1p", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"StartQuantity",
-                start,
-            ),
-            TestProperty::new(
-                vec![("M2p", b"commonXyzInternalTable")],
-                b"Representation",
-                "Volume Mesh",
-            ),
-            TestProperty::new(
-                vec![
-                    ("M2p", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"DeltaTime",
-                "0.05 s",
-            ),
-            TestProperty::new(
-                vec![
-                    ("M2p", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"StartQuantity",
-                start,
-            ),
-            TestProperty::new(
-                vec![("optvol", b"commonXyzInternalTable")],
-                b"Representation",
-                "Volume Mesh",
-            ),
-            TestProperty::new(
-                vec![
-                    ("optvol", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"DeltaTime",
-                "0.2 s",
-            ),
-            TestProperty::new(
-                vec![
-                    ("optvol", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"StartQuantity",
-                start,
-            ),
-            TestProperty::new(
-                vec![("Telescope_p", b"commonXyzInternalTable")],
-                b"Representation",
-                "Volume Mesh",
-            ),
-            TestProperty::new(
-                vec![
-                    ("Telescope_p", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"DeltaTime",
-                "0.05 s",
-            ),
-            TestProperty::new(
-                vec![
-                    ("Telescope_p", b"commonXyzInternalTable"),
-                    ("Update", b"commonTableUpdate"),
-                ],
-                b"StartQuantity",
-                start,
-            ),
-            TestProperty::new(
-                vec![("AMG Linear Solver", b"commonAMGLinearSolver")],
-                b"CycleOption",
-                "AMGCycleOption.V_CYCLE",
-            ),
-            TestProperty::new(vec![("Region 1", b"commonRegion")], b"Parts", parts_as_str),
-            TestProperty::new(
-                vec![("Trimmer", b"trimmerTrimmerMeshingModel")],
-                b"CoordinateSystem",
-                "Laboratory->TCS",
-            ),
-        ];
-        let mut checklist = CheckList::try_from(Tests::new(report, test_props))?;
-        checklist.push(tcs);
-        checklist.push(tcs0);
-        checklist.push(ducts);
-        checklist.push(ws);
-        checklist.push(instvol);
-
-        let folder_path = if folder {
-            let folder = format!(
-                "{case}@{}",
-                checklist.pass().then_some("PASS").unwrap_or("FAIL")
+    println!("CHECKING {}...", case.to_uppercase());
+
+    let tcs = check_tcs(report, &case)?;
+    let tcs0 = check_tcs0(report, &case)?;
+
+    let ducts = TestProperty::new(vec![("ducts", b"commonBoundary")], b"PartSurfaces", "")
+        .check_ducts(report)?;
+    let ws =
+        TestProperty::new(vec![("ws", b"commonBoundary")], b"PartSurfaces", "").check_ws(report)?;
+    let instvol = TestProperty::new(vec![("instvol", b"commonBoundary")], b"PartSurfaces", "")
+        .check_instvol(report)?;
+    let stripped_case = Case::new(&case);
+    let parts = stripped_case.parts();
+    let parts_as_str = parts.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+
+    let test_props = vec![
+        TestProperty::new(
+            vec![("Umax", b"commonUserFieldFunction")],
+            b"Definition",
+            u_max,
+        ),
+        TestProperty::new(
+            vec![(
+                "Maximum Physical Time",
+                b"commonPhysicalTimeStoppingCriterion",
+            )],
+            b"MaximumTime",
+            duration,
+        ),
+        TestProperty::new(
+            vec![("T_upwind", b"basereportSumReport")],
+            b"Representation",
+            "Volume Mesh",
+        ),
+        TestProperty::new(
+            vec![("RI_tel", b"visScene"), ("Scalar 1", b"visScalarDisplayer")],
+            b"Representation",
+            ["Volume Mesh", "Latest Surface/Volume"],
+        ),
+        TestProperty::new(
+            vec![("RI_tel", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"DeltaTime",
+            "0.2 s",
+        ),
+        TestProperty::new(
+            vec![("RI_tel", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"StartQuantity",
+            scene_start,
+        ),
+        TestProperty::new(
+            vec![
+                ("RI_wind", b"visScene"),
+                ("Scalar 1", b"visScalarDisplayer"),
+            ],
+            b"Representation",
+            ["Volume Mesh", "Latest Surface/Volume"],
+        ),
+        TestProperty::new(
+            vec![("RI_wind", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"DeltaTime",
+            "0.2 s",
+        ),
+        TestProperty::new(
+            vec![("RI_wind", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"StartQuantity",
+            scene_start,
+        ),
+        TestProperty::new(
+            vec![
+                ("vort_tel", b"visScene"),
+                ("Scalar 1", b"visScalarDisplayer"),
+            ],
+            b"Representation",
+            ["Volume Mesh", "Latest Surface/Volume"],
+        ),
+        TestProperty::new(
+            vec![("vort_tel", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"DeltaTime",
+            "0.2 s",
+        ),
+        TestProperty::new(
+            vec![("vort_tel", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"StartQuantity",
+            scene_start,
+        ),
+        TestProperty::new(
+            vec![
+                ("vort_wind", b"visScene"),
+                ("Scalar 1", b"visScalarDisplayer"),
+            ],
+            b"Representation",
+            ["Volume Mesh", "Latest Surface/Volume"],
+        ),
+        TestProperty::new(
+            vec![("vort_wind", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"DeltaTime",
+            "0.2 s",
+        ),
+        TestProperty::new(
+            vec![("vort_wind", b"visScene"), ("Update", b"visSceneUpdate")],
+            b"StartQuantity",
+            scene_start,
+        ),
+        TestProperty::new(
+            vec![("M1p", b"commonXyzInternalTable")],
+            b"Representation",
+            "Volume Mesh",
+        ),
+        TestProperty::new(
+            vec![
+                ("M1p", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"DeltaTime",
+            "0.05 s",
+        ),
+        TestProperty::new(
+            vec![
+                ("M1p", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"StartQuantity",
+            start,
+        ),
+        TestProperty::new(
+            vec![("M2p", b"commonXyzInternalTable")],
+            b"Representation",
+            "Volume Mesh",
+        ),
+        TestProperty::new(
+            vec![
+                ("M2p", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"DeltaTime",
+            "0.05 s",
+        ),
+        TestProperty::new(
+            vec![
+                ("M2p", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"StartQuantity",
+            start,
+        ),
+        TestProperty::new(
+            vec![("optvol", b"commonXyzInternalTable")],
+            b"Representation",
+            "Volume Mesh",
+        ),
+        TestProperty::new(
+            vec![
+                ("optvol", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"DeltaTime",
+            "0.2 s",
+        ),
+        TestProperty::new(
+            vec![
+                ("optvol", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"StartQuantity",
+            start,
+        ),
+        TestProperty::new(
+            vec![("Telescope_p", b"commonXyzInternalTable")],
+            b"Representation",
+            "Volume Mesh",
+        ),
+        TestProperty::new(
+            vec![
+                ("Telescope_p", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"DeltaTime",
+            "0.05 s",
+        ),
+        TestProperty::new(
+            vec![
+                ("Telescope_p", b"commonXyzInternalTable"),
+                ("Update", b"commonTableUpdate"),
+            ],
+            b"StartQuantity",
+            start,
+        ),
+        TestProperty::new(
+            vec![("AMG Linear Solver", b"commonAMGLinearSolver")],
+            b"CycleOption",
+            "AMGCycleOption.V_CYCLE",
+        ),
+        TestProperty::new(vec![("Region 1", b"commonRegion")], b"Parts", parts_as_str),
+        TestProperty::new(
+            vec![("Trimmer", b"trimmerTrimmerMeshingModel")],
+            b"CoordinateSystem",
+            "Laboratory->TCS",
+        ),
+    ];
+    let mut checklist = CheckList::try_from(Tests::new(report, test_props))?;
+    checklist.push(tcs);
+    checklist.push(tcs0);
+    checklist.push(ducts);
+    checklist.push(ws);
+    checklist.push(instvol);
+
+    let folder_path = if folder {
+        let folder_path = case_folder.record(checklist.pass().into())?;
+        println!("Writing checklist to {folder_path:?}");
+        let mut file = File::create(folder_path.join("checklist.txt"))?;
+        writeln!(&mut file, "CHECKING {:}", case.to_lowercase())?;
+        write!(&mut file, "{checklist}")?;
+        Some(folder_path)
+    } else {
+        println!("{checklist}");
+        None
+    };
+
+    if !no_scenes && checklist.pass() {
+        write_scenes(case_path, options, &root, folder_path.as_deref())?;
+    }
+    Ok(if checklist.pass() {
+        Status::Pass
+    } else {
+        Status::Fail
+    })
+}
+/// Generates the scenes hardcopies of a sim and moves them into the checklist folder if any, or into `root`
+fn write_scenes(
+    case_path: &Path,
+    options: &CheckOptions,
+    root: &Path,
+    folder_path: Option<&Path>,
+) -> anyhow::Result<()> {
+    let CheckOptions {
+        ref outputs,
+        ref run,
+        ref scenes_macro,
+        ..
+    } = *options;
+    let case = case_name(case_path);
+    let output_dir = check_dir(&case);
+    println!("Writing RI_tel, RI_wind, vort_tel, vort_wind hardcopies ...");
+    let scenes = scenes_macro.outputs(&output_dir, &outputs.scenes);
+    let artifacts = ["RI_tel", "RI_wind", "vort_tel", "vort_wind"]
+        .map(|scene| {
+            Artifact::new(
+                scenes.join(format!("{scene}.png")),
+                Some(if folder_path.is_some() {
+                    format!("{scene}.png")
+                } else {
+                    format!("{case}_{scene}.png")
+                }),
+            )
+        })
+        .to_vec();
+    let _guard = scenes_macro.lock();
+    fs::create_dir_all(&output_dir)?;
+    let played = run
+        .new_macro(case_path, &scenes_macro.path)?
+        .log_dir(outputs.root.join("logs"))
+        .output_dir(&output_dir)
+        .artifacts(folder_path.unwrap_or(root), artifacts)
+        .play();
+    let _ = fs::remove_dir_all(&output_dir);
+    played.with_context(|| format!("failed to generate scenes {case}"))?;
+    Ok(())
+}
+
+/// Sims of the cases of a campaign, with the root directory of their checklist folders
+fn campaign_sims(campaign: &Campaign, outputs: &OutputConfig) -> Vec<(PathBuf, PathBuf)> {
+    let root = campaign_root(campaign, outputs);
+    campaign
+        .cases()
+        .iter()
+        .map(|case| (campaign.sim(case), root.clone()))
+        .collect()
+}
+
+/// Root directory of the checklist folders of the cases of a campaign,
+/// the same as `check` on the source directory
+fn campaign_root(campaign: &Campaign, outputs: &OutputConfig) -> PathBuf {
+    match outputs.layout {
+        Layout::Nested => outputs
+            .root
+            .join(campaign.source.file_name().unwrap_or_default()),
+        Layout::Flat => outputs.root.clone(),
+    }
+}
+
+/// Source and destination of a copy step, with the placeholders of the case replaced
+///
+/// The copy is named after `from`, the sim by default
+fn copy_paths(
+    sim: &Path,
+    from: Option<&Path>,
+    to: &Path,
+    variables: &Variables,
+) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let render = |path: &Path| -> anyhow::Result<PathBuf> {
+        let text = path.to_string_lossy();
+        Ok(variables
+            .render(&text)
+            .map_err(|placeholder| {
+                anyhow::anyhow!("no value for {{{{{placeholder}}}}} in {text:?}")
+            })?
+            .into())
+    };
+    let from = match from {
+        Some(from) => render(from)?,
+        None => sim.to_path_buf(),
+    };
+    let name = from
+        .file_name()
+        .with_context(|| format!("{from:?} is not a file"))?;
+    let to = render(to)?.join(name);
+    Ok((from, to))
+}
+
+/// Runs the steps of a pipeline on the sims and records the status of each step
+fn run_pipeline(
+    sims: Vec<(PathBuf, PathBuf)>,
+    pipeline_path: &Path,
+    options: &CheckOptions,
+    jobs: usize,
+) -> anyhow::Result<()> {
+    let pipeline = Pipeline::load(pipeline_path)?;
+    let variables = |sim: &Path| Variables::new(&case_name(sim), &options.outputs.root);
+    if options.list {
+        for (sim, _) in &sims {
+            println!("{}", sim.display());
+        }
+        return Ok(());
+    }
+    if options.run.dry_run {
+        for (sim, root) in sims {
+            for step in &pipeline.steps {
+                println!("{}: {} ({})", case_name(&sim), step.name, step.action);
+                match &step.action {
+                    Action::Macro { java } => {
+                        println!("{}", options.run.new_macro(&sim, java)?.dry_run()?)
+                    }
+                    Action::Check => dry_run_check(&sim, options, root.clone())?,
+                    Action::Scenes => println!(
+                        "{}",
+                        options
+                            .run
+                            .new_macro(&sim, &options.scenes_macro.path)?
+                            .log_dir(options.outputs.root.join("logs"))
+                            .output_dir(check_dir(&case_name(&sim)))
+                            .dry_run()?
+                    ),
+                    Action::Copy { from, to } => {
+                        let (from, to) = copy_paths(&sim, from.as_deref(), to, &variables(&sim))?;
+                        println!("copy {from:?} to {to:?}");
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+    let n_worker = options.run.workers(jobs)?;
+    if n_worker > 1 {
+        // concurrent copies of the same file would overwrite each other
+        let mut shared = BTreeMap::new();
+        for (sim, _) in &sims {
+            for step in &pipeline.steps {
+                if let Action::Copy { from, to } = &step.action {
+                    let (_, to) = copy_paths(sim, from.as_deref(), to, &variables(sim))?;
+                    let case = case_name(sim);
+                    if let Some(other) = shared.insert(to.clone(), case.clone()) {
+                        anyhow::bail!(
+                            "the step {:?} copies both {other} and {case} to {to:?} concurrently, \
+                            use a placeholder such as {{{{case}}}} in its paths or --jobs 1",
+                            step.name
+                        );
+                    }
+                }
+            }
+        }
+    }
+    let n_sim = sims.len();
+    println!(
+        "running {} steps on {n_sim} sims with {n_worker} workers",
+        pipeline.steps.len()
+    );
+    let mut results = vec![];
+    run_concurrently(
+        n_worker,
+        sims,
+        |(sim, root)| {
+            let case = case_name(&sim);
+            let statuses = pipeline.run(|step| {
+                println!("{case}: {} ({}) ...", step.name, step.action);
+                match &step.action {
+                    Action::Macro { java } => {
+                        catch_panic(|| Ok(options.run.new_macro(&sim, java)?.play()?))?;
+                    }
+                    Action::Check => {
+                        match catch_panic(|| check_sim(&sim, options, root.clone()))? {
+                            Status::Pass => (),
+                            status => anyhow::bail!("checklist {status}"),
+                        }
+                    }
+                    Action::Scenes => {
+                        let folder = CaseFolder::new(&root, &case);
+                        let folder_path = options
+                            .folder
+                            .then(|| folder.verdict().map(|verdict| folder.path(verdict)))
+                            .flatten();
+                        catch_panic(|| write_scenes(&sim, options, &root, folder_path.as_deref()))?;
+                    }
+                    Action::Copy { from, to } => {
+                        let (from, to) = copy_paths(&sim, from.as_deref(), to, &variables(&sim))?;
+                        println!("{case}: copying {from:?} to {to:?} ...");
+                        fs::create_dir_all(to.parent().unwrap())
+                            .and_then(|_| fs::copy(&from, &to))
+                            .with_context(|| format!("failed to copy {from:?} to {to:?}"))?;
+                    }
+                }
+                Ok(())
+            });
+            (case, statuses)
+        },
+        |(case, statuses)| {
+            let i = results.len() + 1;
+            println!(
+                "[{i}/{n_sim}] {case}: {}",
+                pipeline
+                    .steps
+                    .iter()
+                    .zip(&statuses)
+                    .map(|(step, status)| format!("{} {status}", step.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
             );
-            let folder_path = root.join(folder);
-            println!("Writing checklist to {folder_path:?}");
-            fs::create_dir_all(&folder_path)?;
-            let mut file = File::create(folder_path.join("checklist.txt"))?;
-            writeln!(&mut file, "CHECKING {:}", case.to_lowercase())?;
-            write!(&mut file, "{checklist}")?;
-            Some(folder_path)
+            results.push((case, statuses));
+        },
+    );
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let width = results
+        .iter()
+        .map(|(case, _)| case.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let widths: Vec<_> = pipeline
+        .steps
+        .iter()
+        .map(|step| step.name.len().max(7))
+        .collect();
+    let mut table = format!("{:<width$}", "case");
+    for (step, w) in pipeline.steps.iter().zip(&widths) {
+        table.push_str(&format!("  {:<w$}", step.name));
+    }
+    table = table.trim_end().to_string() + "\n";
+    let mut messages = vec![];
+    for (case, statuses) in &results {
+        let mut row = format!("{case:<width$}");
+        for ((step, status), w) in pipeline.steps.iter().zip(statuses).zip(&widths) {
+            row.push_str(&format!("  {:<w$}", status.to_string()));
+            if let StepStatus::Failed(message) | StepStatus::Skipped(message) = status {
+                messages.push(format!("{case} {}: {message}", step.name));
+            }
+        }
+        table.push_str(row.trim_end());
+        table.push('\n');
+    }
+    for message in &messages {
+        table.push_str(message);
+        table.push('\n');
+    }
+    print!("{table}");
+
+    let record = options.outputs.root.join("pipelines").join(format!(
+        "{}_{}.txt",
+        now(),
+        pipeline_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    ));
+    fs::create_dir_all(record.parent().unwrap())
+        .and_then(|_| fs::write(&record, &table))
+        .with_context(|| format!("failed to write the pipeline record {record:?}"))?;
+    println!("pipeline status written to {record:?}");
+
+    let n_fail = results
+        .iter()
+        .filter(|(_, statuses)| statuses.iter().any(|status| !status.is_done()))
+        .count();
+    if n_fail > 0 {
+        anyhow::bail!("the pipeline failed on {n_fail} of {n_sim} sims");
+    }
+    Ok(())
+}
+
+/// Plays the macro of a campaign on its cases and collects the artifacts
+fn batch(
+    campaign_path: &Path,
+    jobs: usize,
+    force: bool,
+    scheduler: Option<SchedulerKind>,
+    run: &RunArgs,
+) -> anyhow::Result<()> {
+    let config = config();
+    let campaign = Campaign::load(campaign_path)?;
+    let java = campaign.java()?;
+    let mut run = run.clone();
+    run.starccm_version = run.starccm_version.or(campaign.version.clone());
+    let scheduler = scheduler.or(config.scheduler.kind);
+    let n_worker = if scheduler.is_some() {
+        jobs
+    } else {
+        run.workers(jobs)?
+    };
+    run.prefix |= n_worker > 1;
+    let variables = |case: &str| Variables::new(case, &config.output.root);
+
+    let cases = campaign.cases();
+    let mut sims = vec![];
+    for case in &cases {
+        if let Some(destination) = campaign.destination.as_deref()
+            && !force
+        {
+            // the files matching the artifact patterns are not known before the run
+            let targets: Vec<_> = campaign
+                .artifacts(case, &variables(case))?
+                .iter()
+                .filter_map(|artifact| artifact.target(case, destination))
+                .collect();
+            if !targets.is_empty() && targets.iter().all(|target| target.exists()) {
+                println!("{case}: artifacts found in {destination:?}, skipping");
+                continue;
+            }
+        }
+        sims.push(campaign.sim(case));
+    }
+    let n_skip = cases.len() - sims.len();
+    println!("{} cases, {n_skip} skipped", cases.len());
+    if n_worker > 1 {
+        // concurrent runs writing to the same path would collect each other's files
+        let mut shared = BTreeMap::new();
+        for sim in &sims {
+            let case = case_name(sim);
+            for artifact in campaign.artifacts(&case, &variables(&case))? {
+                if !artifact.from.is_absolute() {
+                    continue;
+                }
+                if let Some(other) = shared.insert(artifact.from.clone(), case.clone()) {
+                    anyhow::bail!(
+                        "the artifact {:?} is written by both {other} and {case} which run concurrently, \
+                        save it to {{{{work_dir}}}} or use --jobs 1",
+                        artifact.from
+                    );
+                }
+            }
+        }
+    }
+    let new_macro = |sim: &Path| -> anyhow::Result<Macro> {
+        let case = case_name(sim);
+        let artifacts = campaign.artifacts(&case, &variables(&case))?;
+        let play = run.new_macro(sim, java)?.scheduler(scheduler);
+        Ok(if artifacts.is_empty() {
+            play
         } else {
-            println!("{checklist}");
-            None
-        };
+            play.artifacts(campaign.destination()?, artifacts)
+        })
+    };
+    if run.dry_run {
+        for sim in &sims {
+            println!("{}", new_macro(sim)?.dry_run()?);
+        }
+        return Ok(());
+    }
+    if sims.is_empty() {
+        return Ok(());
+    }
+    let record = config.output.root.join("batch").join(format!(
+        "{}_{}.txt",
+        now(),
+        campaign_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    ));
+    play_all(
+        sims,
+        java,
+        n_worker,
+        |sim| {
+            anyhow::ensure!(sim.is_file(), "sim {sim:?} not found");
+            Ok(new_macro(sim)?.play()?)
+        },
+        Some(&record),
+    )
+}
+
+/// Prints the status of the cases of a campaign and flags the missing and extra sims
+fn status(campaign_path: &Path) -> anyhow::Result<()> {
+    let config = config();
+    let campaign = Campaign::load(campaign_path)?;
+    let root = campaign_root(&campaign, &config.output);
+    let cases = campaign.cases();
+    let width = cases.iter().map(String::len).max().unwrap_or(0);
+    let mut counts = BTreeMap::new();
+    println!("{:<width$}  status", "case");
+    for case in &cases {
+        let status = campaign.status(case, &root, &Variables::new(case, &config.output.root))?;
+        println!("{case:<width$}  {status}");
+        *counts.entry(status).or_insert(0) += 1;
+    }
+    println!(
+        "{} cases: {}",
+        cases.len(),
+        counts
+            .iter()
+            .map(|(status, count)| format!("{count} {status}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let missing: Vec<_> = cases
+        .iter()
+        .map(|case| campaign.sim(case))
+        .filter(|sim| !sim.is_file())
+        .collect();
+    if !missing.is_empty() {
+        println!("missing sims:");
+        for sim in missing {
+            println!("  {}", sim.display());
+        }
+    }
+    let extra = campaign.extra_sims()?;
+    if !extra.is_empty() {
+        println!("extra sims, not cases of the campaign:");
+        for sim in extra {
+            println!("  {}", sim.display());
+        }
+    }
+    Ok(())
+}
+
+/// Value of a `--<flag>`/`--no-<flag>` pair of options, the configuration value if neither is given
+fn flag(on: bool, off: bool, config: bool) -> bool {
+    match (on, off) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => config,
+    }
+}
+
+fn case_path(case: &Option<String>) -> anyhow::Result<&Path> {
+    case.as_deref()
+        .map(Path::new)
+        .context("missing the full path to a CFD sim file")
+}
+
+fn macros(command: MacrosCommands) -> anyhow::Result<()> {
+    let dir = &config().macros.dir;
+    match command {
+        MacrosCommands::List => {
+            println!("macros directory: {dir:?}");
+            for bundled in BUNDLED_MACROS {
+                println!("{:<20} {}", bundled.name, bundled.status(dir)?);
+            }
+        }
+        MacrosCommands::Export { dir: to, force } => {
+            let to = to.as_ref().unwrap_or(dir);
+            for bundled in BUNDLED_MACROS {
+                println!("exported {:?}", bundled.export(to, force)?);
+            }
+        }
+    }
+    Ok(())
+}
 
-        if !no_scenes && checklist.pass() {
-            println!("Writing RI_tel, RI_wind, vort_tel, vort_wind hardcopies ...");
-            Macro::new(
-                case_path,
-                Path::new(&*STARCCM_MACROS).join("scenes_views.java"),
-            )?
-            .play()
-            .expect(&format!("failed to generate scenes {case}"));
-            for scene in ["RI_tel", "RI_wind", "vort_tel", "vort_wind"] {
-                let root = Path::new(env!("HOME")).join("Desktop");
-                if let Err(e) = fs::rename(
-                    root.join(format!("{scene}.png")),
-                    if let Some(ref folder) = folder_path {
-                        folder.join(format!("{scene}.png"))
+fn cache(command: CacheCommands) -> anyhow::Result<()> {
+    let cache = ReportCache::default();
+    match command {
+        CacheCommands::List => {
+            let entries = cache.entries()?;
+            println!("{} cached reports in {:?}", entries.len(), cache.root());
+            for entry in entries {
+                println!(
+                    "{} {:<8} size={} modified={} cached={}",
+                    entry.identity.sim.display(),
+                    if entry.is_fresh() {
+                        "[FRESH]"
                     } else {
-                        root.join(format!("{case}_{scene}.png"))
+                        "[STALE]"
                     },
-                ) {
-                    println!("failed to generate scene view hardcopies {e}");
-                }
+                    entry.identity.size,
+                    entry.modified(),
+                    entry.cached().unwrap_or_default()
+                );
+            }
+        }
+        CacheCommands::Prune { all } => {
+            let removed = cache.prune(all)?;
+            for entry in removed.iter() {
+                println!("removed {}", entry.identity.sim.display());
             }
+            println!("{} cached reports removed", removed.len());
         }
     }
     Ok(())
 }
+
+/// Plays a macro on the sims with `play` and `n_worker` concurrent runs
+///
+/// A failed run does not stop the others and a table of the results is printed at the end,
+/// and also written to `record` if given
+fn play_all(
+    paths: Vec<PathBuf>,
+    java: &Path,
+    n_worker: usize,
+    play: impl Fn(&Path) -> anyhow::Result<MacroResult> + Sync,
+    record: Option<&Path>,
+) -> anyhow::Result<()> {
+    let n_sim = paths.len();
+    println!(
+        "applying {} to {n_sim} sims with {n_worker} workers",
+        java.display()
+    );
+    let mut results = vec![];
+    run_concurrently(
+        n_worker,
+        paths,
+        |path| {
+            let case = case_name(&path);
+            let now = Instant::now();
+            let result = catch_panic(|| play(&path));
+            (case, result, now.elapsed())
+        },
+        |(case, result, elapsed)| {
+            let i = results.len() + 1;
+            match &result {
+                Ok(_) => println!("[{i}/{n_sim}] {case}: OK ({})", hms(elapsed)),
+                Err(e) => println!("[{i}/{n_sim}] {case}: FAILED ({}) {e:#}", hms(elapsed)),
+            }
+            results.push((case, result, elapsed));
+        },
+    );
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    let width = results
+        .iter()
+        .map(|(case, ..)| case.len())
+        .max()
+        .unwrap_or(0);
+    let mut table = format!(
+        "{:<width$}  {:<9}  {:>8}  message\n",
+        "case", "status", "time"
+    );
+    for (case, result, elapsed) in &results {
+        let (status, message) = match result {
+            Ok(result) => ("OK", result.to_string()),
+            Err(e)
+                if e.downcast_ref::<MacroError>()
+                    .is_some_and(|e| matches!(e, MacroError::Cancelled { .. })) =>
+            {
+                ("CANCELLED", String::new())
+            }
+            Err(e) => ("FAILED", format!("{e:#}")),
+        };
+        let row = format!(
+            "{case:<width$}  {status:<9}  {:>8}  {message}",
+            hms(*elapsed)
+        );
+        table.push_str(row.trim_end());
+        table.push('\n');
+    }
+    print!("{table}");
+    if let Some(record) = record {
+        fs::create_dir_all(record.parent().unwrap())
+            .and_then(|_| fs::write(record, &table))
+            .with_context(|| format!("failed to write the results to {record:?}"))?;
+        println!("results written to {record:?}");
+    }
+    let n_fail = results
+        .iter()
+        .filter(|(_, result, _)| result.is_err())
+        .count();
+    if n_fail > 0 {
+        anyhow::bail!("{n_fail} of {n_sim} macros failed");
+    }
+    Ok(())
+}
+
+/// Formats a duration as `hh:mm:ss`
+fn hms(duration: Duration) -> String {
+    let s = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    handle_interrupts()?;
+    Config::load(cli.config.as_deref())?.set();
+    let config = config();
     match cli.command {
         Commands::Check {
             report,
             folder,
+            no_folder,
+            scenes,
             no_scenes,
+            jobs,
+            refresh,
+            hash,
+            no_hash,
+            recheck,
+            output,
+            scenes_source,
+            layout,
+            select,
+            mut run,
+        } => {
+            let mut outputs = config.output.clone();
+            outputs.root = output.unwrap_or(outputs.root);
+            outputs.scenes = scenes_source.unwrap_or(outputs.scenes);
+            outputs.layout = layout.unwrap_or(outputs.layout);
+            let root = outputs.root.clone();
+            let case_path = case_path(&cli.case)?;
+            let jobs = jobs.unwrap_or(config.check.jobs);
+            run.prefix |= jobs > 1;
+            let options = CheckOptions {
+                folder: flag(folder, no_folder, config.check.folder),
+                report: report.as_deref(),
+                no_scenes: !flag(scenes, no_scenes, config.check.scenes),
+                refresh,
+                hash: flag(hash, no_hash, config.check.hash),
+                recheck: recheck.unwrap_or(config.check.recheck),
+                cache: ReportCache::default(),
+                outputs,
+                filter: select.filter()?,
+                list: select.list,
+                run,
+                report_macro: CheckMacro::new(BundledMacro::REPORT)?,
+                scenes_macro: CheckMacro::new(BundledMacro::SCENES_VIEWS)?,
+            };
+            checklist(case_path, &options, jobs, root)?;
+        }
+        Commands::PlayMacro {
+            java,
+            batch,
+            scheduler,
+            recursive,
+            select,
+            mut run,
         } => {
-            let root = Path::new(env!("HOME")).join("Desktop");
-            let case_path = Path::new(&cli.case);
-            checklist(
-                case_path,
-                folder,
-                report.as_ref().map(|r| r.as_str()),
-                no_scenes,
-                root,
-            )?;
-        }
-        Commands::PlayMacro { java, batch } => {
-            let case_path = Path::new(&cli.case);
+            let case_path = case_path(&cli.case)?;
+            let scheduler = scheduler.or(config.scheduler.kind);
+            // the outputs of the macros played in parallel are interleaved
+            run.prefix |= batch.is_some();
+            let new_macro = |path: &Path| -> anyhow::Result<Macro> {
+                Ok(run.new_macro(path, &java)?.scheduler(scheduler))
+            };
             if case_path.is_dir() {
-                match batch {
-                    Some(None) => {
-                        let mut macros = vec![];
-                        for entry in fs::read_dir(case_path)? {
-                            let path = entry?.path();
-                            if path.is_file() {
-                                println!("applying {java} to {path:?}");
-                                macros.push(Macro::new(&path, &java)?);
-                            }
-                        }
-                        for h in macros
-                            .into_iter()
-                            .map(|r#macro| thread::spawn(|| r#macro.play()))
-                            .collect::<Vec<_>>()
-                        {
-                            h.join().unwrap()?;
-                        }
+                let paths = select.filter()?.select(case_path, recursive)?;
+                if select.list {
+                    for path in &paths {
+                        println!("{}", path.display());
                     }
-                    Some(Some(n)) => {
-                        let entries: Vec<_> = fs::read_dir(case_path)?.collect();
-                        for batch in entries.chunks(n) {
-                            let mut macros = vec![];
-                            for entry in batch {
-                                let path = entry.as_ref().unwrap().path();
-                                if path.is_file() {
-                                    println!("applying {java} to {path:?}");
-                                    macros.push(Macro::new(&path, &java)?);
-                                }
-                            }
-                            for h in macros
-                                .into_iter()
-                                .map(|r#macro| thread::spawn(|| r#macro.play()))
-                                .collect::<Vec<_>>()
-                            {
-                                h.join().unwrap()?;
-                            }
-                        }
-                    }
-
-                    None => {
-                        for entry in fs::read_dir(case_path)? {
-                            let path = entry?.path();
-                            Macro::new(&path, &java)?.play()?;
-                        }
+                    return Ok(());
+                }
+                if run.dry_run {
+                    for path in &paths {
+                        println!("{}", new_macro(path)?.dry_run()?);
                     }
+                    return Ok(());
                 }
+                let n_worker = match batch {
+                    None => 1,
+                    Some(None) => {
+                        run.workers(thread::available_parallelism().map_or(1, |n| n.get()))?
+                    }
+                    // the jobs submitted to a scheduler do not run on the local machine
+                    Some(Some(n)) if scheduler.is_some() => n,
+                    Some(Some(n)) => run.workers(n)?,
+                };
+                play_all(
+                    paths,
+                    Path::new(&java),
+                    n_worker,
+                    |path| Ok(new_macro(path)?.play()?),
+                    None,
+                )?;
+            } else if run.dry_run {
+                println!("{}", new_macro(case_path)?.dry_run()?);
             } else {
-                Macro::new(case_path, &java)?.play()?;
+                let result = new_macro(case_path)?.play()?;
+                if !result.is_empty() {
+                    println!("{}: {result}", case_name(case_path));
+                }
+            }
+        }
+        Commands::RunPipeline {
+            pipeline,
+            jobs,
+            folder,
+            no_folder,
+            campaign,
+            select,
+            mut run,
+        } => {
+            let jobs = jobs.unwrap_or(1);
+            run.prefix |= jobs > 1;
+            let campaign = campaign
+                .map(|campaign| Campaign::load(&campaign))
+                .transpose()?;
+            if let Some(campaign) = campaign.as_ref() {
+                run.starccm_version = run.starccm_version.or(campaign.version.clone());
+            }
+            let options = CheckOptions {
+                folder: flag(folder, no_folder, config.check.folder),
+                report: None,
+                no_scenes: true,
+                refresh: false,
+                hash: config.check.hash,
+                // the previous steps may have changed the sims
+                recheck: Recheck::All,
+                cache: ReportCache::default(),
+                outputs: config.output.clone(),
+                filter: select.filter()?,
+                list: select.list,
+                run,
+                report_macro: CheckMacro::new(BundledMacro::REPORT)?,
+                scenes_macro: CheckMacro::new(BundledMacro::SCENES_VIEWS)?,
+            };
+            let mut sims = vec![];
+            match campaign {
+                Some(campaign) => sims.extend(
+                    campaign_sims(&campaign, &options.outputs)
+                        .into_iter()
+                        .filter(|(sim, _)| options.filter.matches(sim)),
+                ),
+                None => collect_sims(
+                    case_path(&cli.case)?,
+                    options.outputs.root.clone(),
+                    options.outputs.layout,
+                    &options.filter,
+                    &mut sims,
+                )?,
+            }
+            run_pipeline(sims, &pipeline, &options, jobs)?;
+        }
+        Commands::Batch {
+            campaign,
+            jobs,
+            force,
+            scheduler,
+            run,
+        } => batch(&campaign, jobs, force, scheduler, &run)?,
+        Commands::Status { campaign } => status(&campaign)?,
+        Commands::Cache { command } => cache(command)?,
+        Commands::Macros { command } => macros(command)?,
+        Commands::Config {
+            command: ConfigCommands::Show,
+        } => {
+            for (key, value, source) in config.values()? {
+                println!("{key:<16} = {value:<40} # {source}");
+            }
+        }
+        Commands::Installations => {
+            println!("default: {}", config.starccm.binary.display());
+            if let Some(version) = config.starccm.version.as_ref() {
+                println!("pinned:  {version} ({})", config.source("starccm.version"));
+            }
+            for installation in Installations::new(config).iter() {
+                println!("{installation}");
+            }
+        }
+        Commands::Doctor => {
+            let diagnostics = diagnose(config);
+            for diagnostic in diagnostics.iter() {
+                println!("{diagnostic}");
+            }
+            let n_fail = diagnostics
+                .iter()
+                .filter(|d| d.health == Health::Fail)
+                .count();
+            if n_fail > 0 {
+                anyhow::bail!("found {n_fail} problems");
             }
+            println!("no problem found");
         }
     }
     Ok(())
@@ -0,0 +1,515 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::Path,
+};
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutcomeError {
+    #[error("invalid log pattern {0:?}")]
+    Regex(String, #[source] regex::Error),
+}
+
+/// Classes of StarCCM+ failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    License,
+    JavaCompile,
+    JavaException,
+    OutOfMemory,
+    MissingSim,
+    VersionMismatch,
+    ServerCrash,
+}
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureKind::License => write!(f, "license"),
+            FailureKind::JavaCompile => write!(f, "java-compile"),
+            FailureKind::JavaException => write!(f, "java-exception"),
+            FailureKind::OutOfMemory => write!(f, "out-of-memory"),
+            FailureKind::MissingSim => write!(f, "missing-sim"),
+            FailureKind::VersionMismatch => write!(f, "version-mismatch"),
+            FailureKind::ServerCrash => write!(f, "server-crash"),
+        }
+    }
+}
+
+/// Outcome of a StarCCM+ run, read from its output
+///
+/// `message` is the line of the output that matched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroOutcome {
+    Success,
+    /// the license could not be checked out
+    License {
+        feature: Option<String>,
+        message: String,
+    },
+    /// the java macro does not compile
+    JavaCompile {
+        line: Option<usize>,
+        message: String,
+    },
+    /// the java macro has thrown an exception
+    JavaException {
+        exception: Option<String>,
+        line: Option<usize>,
+        message: String,
+    },
+    OutOfMemory {
+        message: String,
+    },
+    /// the sim file does not exist
+    MissingSim {
+        path: Option<String>,
+        message: String,
+    },
+    /// the sim has been saved with another StarCCM+ version
+    VersionMismatch {
+        version: Option<String>,
+        message: String,
+    },
+    /// the StarCCM+ server has crashed
+    ServerCrash {
+        message: String,
+    },
+    /// StarCCM+ has exited with an error but its output does not match any pattern
+    ExitStatus {
+        code: Option<i32>,
+    },
+}
+impl MacroOutcome {
+    pub fn is_success(&self) -> bool {
+        *self == MacroOutcome::Success
+    }
+    /// Class of the failure, if any
+    pub fn kind(&self) -> Option<FailureKind> {
+        match self {
+            MacroOutcome::Success | MacroOutcome::ExitStatus { .. } => None,
+            MacroOutcome::License { .. } => Some(FailureKind::License),
+            MacroOutcome::JavaCompile { .. } => Some(FailureKind::JavaCompile),
+            MacroOutcome::JavaException { .. } => Some(FailureKind::JavaException),
+            MacroOutcome::OutOfMemory { .. } => Some(FailureKind::OutOfMemory),
+            MacroOutcome::MissingSim { .. } => Some(FailureKind::MissingSim),
+            MacroOutcome::VersionMismatch { .. } => Some(FailureKind::VersionMismatch),
+            MacroOutcome::ServerCrash { .. } => Some(FailureKind::ServerCrash),
+        }
+    }
+    /// Builds the outcome of a `kind` of failure from the named groups of the pattern
+    fn new(kind: FailureKind, captures: &BTreeMap<String, String>, message: &str) -> Self {
+        let get = |name: &str| captures.get(name).cloned();
+        let line = get("line").and_then(|line| line.parse().ok());
+        let message = message.trim().to_string();
+        match kind {
+            FailureKind::License => MacroOutcome::License {
+                feature: get("feature"),
+                message,
+            },
+            FailureKind::JavaCompile => MacroOutcome::JavaCompile { line, message },
+            FailureKind::JavaException => MacroOutcome::JavaException {
+                exception: get("exception"),
+                line,
+                message,
+            },
+            FailureKind::OutOfMemory => MacroOutcome::OutOfMemory { message },
+            FailureKind::MissingSim => MacroOutcome::MissingSim {
+                path: get("path"),
+                message,
+            },
+            FailureKind::VersionMismatch => MacroOutcome::VersionMismatch {
+                version: get("version"),
+                message,
+            },
+            FailureKind::ServerCrash => MacroOutcome::ServerCrash { message },
+        }
+    }
+}
+impl Display for MacroOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroOutcome::Success => write!(f, "success"),
+            MacroOutcome::License { feature, message } => match feature {
+                Some(feature) => write!(f, "license checkout of {feature} failed: {message}"),
+                None => write!(f, "license checkout failed: {message}"),
+            },
+            MacroOutcome::JavaCompile { line, message } => match line {
+                Some(line) => write!(f, "java macro compilation failed at line {line}: {message}"),
+                None => write!(f, "java macro compilation failed: {message}"),
+            },
+            MacroOutcome::JavaException {
+                exception,
+                line,
+                message,
+            } => {
+                write!(
+                    f,
+                    "java macro threw {}",
+                    exception.as_deref().unwrap_or("an exception")
+                )?;
+                if let Some(line) = line {
+                    write!(f, " at line {line}")?;
+                }
+                write!(f, ": {message}")
+            }
+            MacroOutcome::OutOfMemory { message } => write!(f, "out of memory: {message}"),
+            MacroOutcome::MissingSim { path, message } => match path {
+                Some(path) => write!(f, "sim {path} not found: {message}"),
+                None => write!(f, "sim not found: {message}"),
+            },
+            MacroOutcome::VersionMismatch { version, message } => match version {
+                Some(version) => write!(f, "sim saved with StarCCM+ {version}: {message}"),
+                None => write!(f, "StarCCM+ version mismatch: {message}"),
+            },
+            MacroOutcome::ServerCrash { message } => write!(f, "server crashed: {message}"),
+            MacroOutcome::ExitStatus { code } => match code {
+                Some(code) => write!(f, "StarCCM+ exited with code {code}"),
+                None => write!(f, "StarCCM+ was killed by a signal"),
+            },
+        }
+    }
+}
+
+/// User pattern of the log analyzer
+///
+/// e.g.
+/// ```toml
+/// [patterns.flexlm-down]
+/// kind = "license"
+/// regex = 'Cannot connect to license server system.*feature: (?P<feature>\w+)'
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternConfig {
+    pub kind: FailureKind,
+    /// Regular expression matched against each line of the output
+    pub regex: String,
+    /// Regular expression matched against the lines following the matching line to get more details
+    pub detail: Option<String>,
+}
+
+/// Pattern of the log analyzer
+///
+/// The named groups `feature`, `line`, `exception`, `path` and `version` of the regular expressions
+/// give the details of the [MacroOutcome]
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    kind: FailureKind,
+    regex: Regex,
+    detail: Option<Regex>,
+}
+impl Pattern {
+    pub fn new(kind: FailureKind, regex: &str, detail: Option<&str>) -> Result<Self, OutcomeError> {
+        let compile =
+            |regex: &str| Regex::new(regex).map_err(|e| OutcomeError::Regex(regex.to_string(), e));
+        Ok(Self {
+            kind,
+            regex: compile(regex)?,
+            detail: detail.map(compile).transpose()?,
+        })
+    }
+    /// Matches the pattern against the output lines
+    ///
+    /// If the file name of the played macro is given,
+    /// the details with a `file` group are only taken from the lines referring to the macro
+    fn analyze(&self, lines: &[&str], java: Option<&str>) -> Option<MacroOutcome> {
+        let (i, captures) = lines
+            .iter()
+            .enumerate()
+            .find_map(|(i, line)| self.regex.captures(line).map(|c| (i, c)))?;
+        let mut named = named_groups(&self.regex, &captures);
+        if let Some(detail) = self.detail.as_ref()
+            && let Some(captures) = lines[i + 1..]
+                .iter()
+                .take(DETAIL_LINES)
+                .flat_map(|line| detail.captures_iter(line))
+                .find(|captures| match (java, captures.name("file")) {
+                    (Some(java), Some(file)) => file.as_str() == java,
+                    _ => true,
+                })
+        {
+            for (name, value) in named_groups(detail, &captures) {
+                named.entry(name).or_insert(value);
+            }
+        }
+        Some(MacroOutcome::new(self.kind, &named, lines[i]))
+    }
+}
+
+/// Number of lines, following a matching line, searched for details
+const DETAIL_LINES: usize = 20;
+
+fn named_groups(regex: &Regex, captures: &Captures) -> BTreeMap<String, String> {
+    regex
+        .capture_names()
+        .flatten()
+        .filter_map(|name| {
+            captures
+                .name(name)
+                .map(|m| (name.to_string(), m.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// Failures detected in the output of a run that has exited with code 0, by default
+///
+/// A macro may log an exception it has caught or a sim it has not found and still succeed
+pub const ZERO_EXIT_FAILURES: [FailureKind; 5] = [
+    FailureKind::License,
+    FailureKind::JavaCompile,
+    FailureKind::OutOfMemory,
+    FailureKind::VersionMismatch,
+    FailureKind::ServerCrash,
+];
+
+/// Built-in patterns as `(kind, regex, detail)`, in order of precedence
+const PATTERNS: &[(FailureKind, &str, Option<&str>)] = &[
+    (
+        FailureKind::License,
+        r#"(?i)(?:unable to|failed to|cannot) check ?out (?:a )?license(?: for)?(?: feature)?:?\s*"?(?P<feature>[\w.-]+)?"#,
+        None,
+    ),
+    (
+        FailureKind::License,
+        r#"(?i)license (?:checkout|request) (?:failed|error).*?(?:feature:?\s*"?(?P<feature>[\w.-]+))?$"#,
+        None,
+    ),
+    (
+        FailureKind::License,
+        r"(?i)flex(?:net|lm) licensing error",
+        Some(r"(?i)feature:\s*(?P<feature>[\w.-]+)"),
+    ),
+    (
+        FailureKind::License,
+        r"(?i)cannot connect to license server",
+        Some(r"(?i)feature:\s*(?P<feature>[\w.-]+)"),
+    ),
+    (
+        FailureKind::OutOfMemory,
+        r"(?i)java\.lang\.OutOfMemoryError|out of memory|cannot allocate memory|std::bad_alloc",
+        None,
+    ),
+    (
+        FailureKind::JavaCompile,
+        r"(?P<file>[\w$./-]+\.java):(?P<line>\d+): error:",
+        None,
+    ),
+    (
+        FailureKind::JavaCompile,
+        r"(?i)(?:macro )?compilation failed",
+        Some(r"\.java:(?P<line>\d+)"),
+    ),
+    (
+        FailureKind::JavaException,
+        r"(?P<exception>(?:java|javax|star)\.[\w.$]+(?:Exception|Error))\b",
+        Some(r"\((?P<file>[\w$]+\.java):(?P<line>\d+)\)"),
+    ),
+    (
+        FailureKind::MissingSim,
+        r"(?i)(?P<path>\S+\.sim)\W*(?:does not exist|not found|no such file)",
+        None,
+    ),
+    (
+        FailureKind::MissingSim,
+        r"(?i)(?:does not exist|not found|no such file)\W*(?P<path>\S+\.sim)",
+        None,
+    ),
+    (
+        FailureKind::VersionMismatch,
+        r"(?i)(?:saved|created|written) (?:with|by|in) (?:a )?(?:newer|more recent) version(?:.*?(?P<version>\d+\.\d+\.\d+))?",
+        None,
+    ),
+    (
+        FailureKind::VersionMismatch,
+        r"(?i)(?:cannot|unable to) (?:load|open|read) .*?version (?P<version>\d+\.\d+\.\d+)",
+        None,
+    ),
+    (
+        FailureKind::ServerCrash,
+        r"(?i)server process ended unexpectedly|server (?:process )?(?:has )?terminated abnormally",
+        None,
+    ),
+];
+
+/// StarCCM+ output analyzer
+///
+/// The patterns are tried in order, the user patterns first and then the built-in patterns,
+/// and the first pattern matching a line of the output gives the [MacroOutcome]
+#[derive(Debug, Clone)]
+pub struct LogAnalyzer {
+    patterns: Vec<Pattern>,
+    zero_exit_failures: Vec<FailureKind>,
+    java: Option<String>,
+}
+impl LogAnalyzer {
+    /// Builds the analyzer with the user patterns from the configuration followed by the built-in patterns
+    pub fn new(patterns: &BTreeMap<String, PatternConfig>) -> Result<Self, OutcomeError> {
+        let mut analyzer = Self {
+            patterns: vec![],
+            zero_exit_failures: ZERO_EXIT_FAILURES.to_vec(),
+            java: None,
+        };
+        for pattern in patterns.values() {
+            analyzer.push(Pattern::new(
+                pattern.kind,
+                &pattern.regex,
+                pattern.detail.as_deref(),
+            )?);
+        }
+        for (kind, regex, detail) in PATTERNS {
+            analyzer.push(Pattern::new(*kind, regex, *detail)?);
+        }
+        Ok(analyzer)
+    }
+    /// Sets the failures detected in the output of a run that has exited with code 0, [ZERO_EXIT_FAILURES] by default
+    ///
+    /// All the failures are detected in the output of a run that has exited with an error
+    pub fn zero_exit_failures(mut self, kinds: &[FailureKind]) -> Self {
+        self.zero_exit_failures = kinds.to_vec();
+        self
+    }
+    /// Sets the played macro, the line of a java exception being read from the stack frames of the macro
+    pub fn java_macro(mut self, java: &Path) -> Self {
+        self.java = java
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        self
+    }
+    /// Adds a pattern with the lowest precedence
+    pub fn push(&mut self, pattern: Pattern) {
+        self.patterns.push(pattern);
+    }
+    /// Classifies the output of a StarCCM+ run that has exited with the status `code`
+    pub fn analyze(&self, output: &str, code: Option<i32>) -> MacroOutcome {
+        let lines: Vec<_> = output.lines().collect();
+        self.patterns
+            .iter()
+            .filter(|pattern| code != Some(0) || self.zero_exit_failures.contains(&pattern.kind))
+            .find_map(|pattern| pattern.analyze(&lines, self.java.as_deref()))
+            .unwrap_or(if code == Some(0) {
+                MacroOutcome::Success
+            } else {
+                MacroOutcome::ExitStatus { code }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyzer() -> LogAnalyzer {
+        LogAnalyzer::new(&BTreeMap::new()).unwrap()
+    }
+
+    const EXCEPTION: &str = "\
+Loading the sim
+error: java.lang.NullPointerException: scene vort_wind
+    at star.vis.SceneManager.getScene(SceneManager.java:120)
+    at macro.report.execute(report.java:42)
+";
+
+    #[test]
+    fn success_and_exit_status() {
+        assert_eq!(
+            analyzer().analyze("Saving the sim\n", Some(0)),
+            MacroOutcome::Success
+        );
+        assert_eq!(
+            analyzer().analyze("Saving the sim\n", Some(2)),
+            MacroOutcome::ExitStatus { code: Some(2) }
+        );
+        assert_eq!(
+            analyzer().analyze("", None),
+            MacroOutcome::ExitStatus { code: None }
+        );
+    }
+
+    #[test]
+    fn license_feature() {
+        let outcome = analyzer().analyze(
+            "FlexNet Licensing error:-15,570\nFeature: ccmpsuite\n",
+            Some(1),
+        );
+        assert_eq!(outcome.kind(), Some(FailureKind::License));
+        assert!(
+            matches!(outcome, MacroOutcome::License { feature: Some(feature), .. } if feature == "ccmpsuite")
+        );
+        // a license failure is detected even if StarCCM+ has exited with code 0
+        assert_eq!(
+            analyzer()
+                .analyze("FlexNet Licensing error:-15,570\n", Some(0))
+                .kind(),
+            Some(FailureKind::License)
+        );
+    }
+
+    #[test]
+    fn exception_line_from_the_macro_frame() {
+        let outcome = analyzer()
+            .java_macro(Path::new("/macros/report.java"))
+            .analyze(EXCEPTION, Some(1));
+        assert_eq!(
+            outcome,
+            MacroOutcome::JavaException {
+                exception: Some("java.lang.NullPointerException".to_string()),
+                line: Some(42),
+                message: "error: java.lang.NullPointerException: scene vort_wind".to_string(),
+            }
+        );
+        // without the macro, the first frame is taken
+        let outcome = analyzer().analyze(EXCEPTION, Some(1));
+        assert!(matches!(
+            outcome,
+            MacroOutcome::JavaException {
+                line: Some(120),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn zero_exit_failures() {
+        assert_eq!(
+            analyzer().analyze(EXCEPTION, Some(0)),
+            MacroOutcome::Success
+        );
+        let analyzer = analyzer().zero_exit_failures(&[FailureKind::JavaException]);
+        assert_eq!(
+            analyzer.analyze(EXCEPTION, Some(0)).kind(),
+            Some(FailureKind::JavaException)
+        );
+    }
+
+    #[test]
+    fn user_patterns_first() {
+        let patterns = BTreeMap::from([(
+            "solver-diverged".to_string(),
+            PatternConfig {
+                kind: FailureKind::ServerCrash,
+                regex: r"Floating point exception".to_string(),
+                detail: None,
+            },
+        )]);
+        let outcome = LogAnalyzer::new(&patterns).unwrap().analyze(
+            "Floating point exception\njava.lang.OutOfMemoryError\n",
+            Some(1),
+        );
+        assert_eq!(outcome.kind(), Some(FailureKind::ServerCrash));
+        assert!(matches!(
+            LogAnalyzer::new(&BTreeMap::from([(
+                "invalid".to_string(),
+                PatternConfig {
+                    kind: FailureKind::License,
+                    regex: "(".to_string(),
+                    detail: None,
+                },
+            )])),
+            Err(OutcomeError::Regex(..))
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("failed to read the pipeline {0:?}")]
+    Read(PathBuf, #[source] io::Error),
+    #[error("failed to parse the pipeline {0:?}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("the pipeline has no step")]
+    Empty,
+    #[error("step {0:?} is defined twice")]
+    Duplicate(String),
+    #[error("step {step:?} depends on {dependency:?} which is not a previous step")]
+    Dependency { step: String, dependency: String },
+}
+
+/// Action of a pipeline step
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Action {
+    /// Plays a java macro on the sim
+    Macro { java: PathBuf },
+    /// Builds the summary report of the sim and applies the checklist
+    Check,
+    /// Generates the scenes hardcopies
+    Scenes,
+    /// Copies a file, the sim by default, into a directory
+    Copy { from: Option<PathBuf>, to: PathBuf },
+}
+impl Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Macro { java } => write!(f, "macro {}", java.display()),
+            Action::Check => write!(f, "check"),
+            Action::Scenes => write!(f, "scenes"),
+            Action::Copy {
+                from: Some(from),
+                to,
+            } => {
+                write!(f, "copy {} to {}", from.display(), to.display())
+            }
+            Action::Copy { from: None, to } => write!(f, "copy sim to {}", to.display()),
+        }
+    }
+}
+
+/// What happens to the next steps when a step fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnFailure {
+    /// the next steps are not run
+    #[default]
+    Stop,
+    /// the next steps are run, except the ones depending on the failed step
+    Continue,
+}
+
+/// Step of a pipeline
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Step {
+    pub name: String,
+    #[serde(flatten)]
+    pub action: Action,
+    /// Steps that must be done before this one
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub on_failure: OnFailure,
+}
+
+/// Status of a pipeline step
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepStatus {
+    Done,
+    Failed(String),
+    /// a step it depends on is not done
+    Skipped(String),
+    /// the pipeline was stopped by a previous failure
+    NotRun,
+}
+impl StepStatus {
+    pub fn is_done(&self) -> bool {
+        *self == StepStatus::Done
+    }
+}
+impl Display for StepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepStatus::Done => write!(f, "DONE"),
+            StepStatus::Failed(_) => write!(f, "FAILED"),
+            StepStatus::Skipped(_) => write!(f, "SKIPPED"),
+            StepStatus::NotRun => write!(f, "NOT RUN"),
+        }
+    }
+}
+
+/// Ordered steps applied to each sim of a campaign
+///
+/// ```toml
+/// [[steps]]
+/// name = "update"
+/// kind = "macro"
+/// java = "/home/ubuntu/macros/ForceChangeAll.java"
+///
+/// [[steps]]
+/// name = "check"
+/// kind = "check"
+/// on_failure = "continue"
+///
+/// [[steps]]
+/// name = "stage"
+/// kind = "copy"
+/// to = "/home/ubuntu/staging"
+/// depends_on = ["update"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    pub steps: Vec<Step>,
+}
+impl Pipeline {
+    /// Reads and validates a pipeline TOML file
+    ///
+    /// The relative macro paths are relative to the directory of the file
+    pub fn load(path: &Path) -> Result<Self, PipelineError> {
+        let text =
+            fs::read_to_string(path).map_err(|e| PipelineError::Read(path.to_path_buf(), e))?;
+        let mut pipeline: Self =
+            toml::from_str(&text).map_err(|e| PipelineError::Parse(path.to_path_buf(), e))?;
+        pipeline.validate()?;
+        if let Some(dir) = path.parent() {
+            for step in &mut pipeline.steps {
+                if let Action::Macro { java } = &mut step.action {
+                    *java = dir.join(&*java);
+                }
+            }
+        }
+        Ok(pipeline)
+    }
+    /// Checks that the step names are unique and that the steps only depend on previous steps
+    pub fn validate(&self) -> Result<(), PipelineError> {
+        if self.steps.is_empty() {
+            return Err(PipelineError::Empty);
+        }
+        let mut names = BTreeSet::new();
+        for step in &self.steps {
+            if let Some(dependency) = step
+                .depends_on
+                .iter()
+                .find(|dependency| !names.contains(dependency.as_str()))
+            {
+                return Err(PipelineError::Dependency {
+                    step: step.name.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+            if !names.insert(step.name.as_str()) {
+                return Err(PipelineError::Duplicate(step.name.clone()));
+            }
+        }
+        Ok(())
+    }
+    /// Runs the steps in order with `execute`, returning the status of each step
+    pub fn run(&self, mut execute: impl FnMut(&Step) -> anyhow::Result<()>) -> Vec<StepStatus> {
+        let mut statuses: Vec<StepStatus> = Vec::with_capacity(self.steps.len());
+        let mut stopped = false;
+        for step in &self.steps {
+            let status = if stopped {
+                StepStatus::NotRun
+            } else if let Some(dependency) = step.depends_on.iter().find(|dependency| {
+                self.steps
+                    .iter()
+                    .zip(&statuses)
+                    .any(|(step, status)| step.name == **dependency && !status.is_done())
+            }) {
+                StepStatus::Skipped(format!("{dependency} is not done"))
+            } else {
+                match execute(step) {
+                    Ok(()) => StepStatus::Done,
+                    Err(e) => {
+                        stopped = step.on_failure == OnFailure::Stop;
+                        StepStatus::Failed(format!("{e:#}"))
+                    }
+                }
+            };
+            statuses.push(status);
+        }
+        statuses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn pipeline(text: &str) -> Pipeline {
+        toml::from_str(text).unwrap()
+    }
+
+    const STEPS: &str = r#"
+[[steps]]
+name = "update"
+kind = "macro"
+java = "update.java"
+
+[[steps]]
+name = "check"
+kind = "check"
+on_failure = "continue"
+
+[[steps]]
+name = "scenes"
+kind = "scenes"
+depends_on = ["check"]
+
+[[steps]]
+name = "stage"
+kind = "copy"
+to = "staging"
+depends_on = ["update"]
+"#;
+
+    #[test]
+    fn validate() {
+        assert!(pipeline(STEPS).validate().is_ok());
+        assert!(matches!(
+            pipeline("steps = []").validate(),
+            Err(PipelineError::Empty)
+        ));
+        let duplicate = r#"
+[[steps]]
+name = "check"
+kind = "check"
+[[steps]]
+name = "check"
+kind = "scenes"
+"#;
+        assert!(matches!(
+            pipeline(duplicate).validate(),
+            Err(PipelineError::Duplicate(name)) if name == "check"
+        ));
+        // a step may only depend on a previous step
+        let forward = r#"
+[[steps]]
+name = "scenes"
+kind = "scenes"
+depends_on = ["check"]
+[[steps]]
+name = "check"
+kind = "check"
+"#;
+        assert!(matches!(
+            pipeline(forward).validate(),
+            Err(PipelineError::Dependency { step, dependency }) if step == "scenes" && dependency == "check"
+        ));
+    }
+
+    /// Statuses of the steps when the steps named in `failing` fail
+    fn run(pipeline: &Pipeline, failing: &[&str]) -> Vec<StepStatus> {
+        pipeline.run(|step| {
+            if failing.contains(&step.name.as_str()) {
+                anyhow::bail!("{} failed", step.name)
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn run_all_done() {
+        assert_eq!(run(&pipeline(STEPS), &[]), vec![StepStatus::Done; 4]);
+    }
+
+    #[test]
+    fn continue_skips_the_dependent_steps() {
+        assert_eq!(
+            run(&pipeline(STEPS), &["check"]),
+            [
+                StepStatus::Done,
+                StepStatus::Failed("check failed".to_string()),
+                StepStatus::Skipped("check is not done".to_string()),
+                StepStatus::Done,
+            ]
+        );
+    }
+
+    #[test]
+    fn stop_does_not_run_the_next_steps() {
+        assert_eq!(
+            run(&pipeline(STEPS), &["update"]),
+            [
+                StepStatus::Failed("update failed".to_string()),
+                StepStatus::NotRun,
+                StepStatus::NotRun,
+                StepStatus::NotRun,
+            ]
+        );
+    }
+
+    #[test]
+    fn load_relative_macros() {
+        let dir = TempDir::new("pipeline_load_relative_macros");
+        let path = dir.join("pipeline.toml");
+        fs::write(&path, STEPS).unwrap();
+        let pipeline = Pipeline::load(&path).unwrap();
+        assert_eq!(
+            pipeline.steps[0].action,
+            Action::Macro {
+                java: dir.join("update.java")
+            }
+        );
+        assert_eq!(
+            pipeline.steps[3].action,
+            Action::Copy {
+                from: None,
+                to: "staging".into()
+            }
+        );
+    }
+}
@@ -1,67 +1,877 @@
 use std::{
-    env, io,
-    path::{Path, PathBuf},
-    process::Command,
-    string::FromUtf8Error,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{self, Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use crate::STARCCM;
+use crate::{
+    Artifact, ArtifactError, Installations, Job, JobStatus, License, LicenseError, LogAnalyzer,
+    MacroOutcome, MacroResult, OutcomeError, Parallel, ProcessGroup, ResultError, RetryPolicy,
+    SchedulerError, SchedulerKind, Template, TemplateError, Variables, binary_version,
+    collect_artifacts, config, is_cancelled, kill_group, now, sim_version, starccm_version,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum MacroError {
     #[error("failed to run starcccm+ macro")]
     Command(#[from] io::Error),
-    #[error("missing starccm+ podkey")]
-    PODKEY(#[from] env::VarError),
-    #[error(r#"java macro: "{0}" is missing (you can set the path to the macro with the environment variable: "STARCCM_MACROS")"#)]
+    #[error(transparent)]
+    License(#[from] LicenseError),
+    #[error(transparent)]
+    Scheduler(#[from] SchedulerError),
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+    #[error("{case}: {{{{{placeholder}}}}} in the artifact {artifact:?} has no value")]
+    ArtifactPlaceholder {
+        case: String,
+        artifact: PathBuf,
+        placeholder: String,
+    },
+    #[error("{case}: {source}, see the log {log:?}")]
+    Result {
+        case: String,
+        #[source]
+        source: ResultError,
+        log: PathBuf,
+    },
+    #[error("{case}: {source}, see the log {log:?}")]
+    Artifact {
+        case: String,
+        #[source]
+        source: ArtifactError,
+        log: PathBuf,
+    },
+    #[error("{case}: StarCCM+ timed out after {}s, see the log {log:?}", .timeout.as_secs())]
+    Timeout {
+        case: String,
+        timeout: Duration,
+        log: PathBuf,
+    },
+    #[error("{case}: {outcome}, see the log {log:?}")]
+    Outcome {
+        case: String,
+        outcome: MacroOutcome,
+        log: PathBuf,
+    },
+    #[error(transparent)]
+    Analyzer(#[from] OutcomeError),
+    #[error("failed to write the StarCCM+ log {0:?}")]
+    Log(PathBuf, #[source] io::Error),
+    #[error("{case}: StarCCM+ run cancelled")]
+    Cancelled { case: String },
+    #[error("job {id} of {case} {status}, see the log {log:?}")]
+    Job {
+        case: String,
+        id: String,
+        status: JobStatus,
+        log: PathBuf,
+    },
+    #[error(r#"java macro: "{0}" is missing (you can set the path to the macros with the environment variable "STARCCM_MACROS" or the configuration key "macros.dir")"#)]
     Java(PathBuf),
-    #[error("command output message conversion to UTF8 failed")]
-    Ouput(#[from] FromUtf8Error),
-    #[error("StarCCM+ failed with stdout: {0}")]
-    StdOut(String),
-    #[error("StarCCM+ failed with stderr: {0}")]
-    StdErr(String),
+    #[error(
+        "{case} {requires} StarCCM+ {pinned} which is not installed, and {binary:?} is StarCCM+ {} (use --force-version to open it anyway)",
+        .found.as_deref().unwrap_or("of unknown version")
+    )]
+    Version {
+        case: String,
+        /// `is pinned to` or `was saved with`
+        requires: &'static str,
+        pinned: String,
+        binary: PathBuf,
+        found: Option<String>,
+    },
 }
 type Result<T> = std::result::Result<T, MacroError>;
+impl MacroError {
+    /// Outcome of the StarCCM+ run, if it has failed
+    pub fn outcome(&self) -> Option<&MacroOutcome> {
+        match self {
+            MacroError::Outcome { outcome, .. } => Some(outcome),
+            _ => None,
+        }
+    }
+    /// Log of the StarCCM+ run
+    pub fn log(&self) -> Option<&Path> {
+        match self {
+            MacroError::Outcome { log, .. }
+            | MacroError::Result { log, .. }
+            | MacroError::Artifact { log, .. }
+            | MacroError::Timeout { log, .. }
+            | MacroError::Job { log, .. } => Some(log),
+            _ => None,
+        }
+    }
+}
+
+/// Time given to StarCCM+ to exit after `SIGTERM`
+const KILL_GRACE: Duration = Duration::from_secs(10);
+
+/// Working directory of a run, holding the rendered macro template, removed after the run
+struct WorkDir(PathBuf);
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Absolute path, relative to the current directory
+fn absolute(path: &Path) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Destinations of the StarCCM+ output lines
+#[derive(Clone)]
+struct Tee {
+    prefix: Option<String>,
+    log: Arc<Mutex<File>>,
+    license: License,
+}
+impl Tee {
+    /// Streams the lines of a child process pipe to the terminal and to the log until the pipe is closed
+    ///
+    /// Returns the redacted output
+    fn stream(self, pipe: Option<impl Read + Send + 'static>, stderr: bool) -> JoinHandle<String> {
+        thread::spawn(move || {
+            let mut output = String::new();
+            let Some(pipe) = pipe else {
+                return output;
+            };
+            for line in BufReader::new(pipe)
+                .split(b'\n')
+                .map_while(|line| line.ok())
+            {
+                let line = self.license.redact(&String::from_utf8_lossy(&line));
+                let line = line.trim_end_matches('\r');
+                match (self.prefix.as_ref(), stderr) {
+                    (Some(prefix), false) => println!("[{prefix}] {line}"),
+                    (Some(prefix), true) => eprintln!("[{prefix}] {line}"),
+                    (None, false) => println!("{line}"),
+                    (None, true) => eprintln!("{line}"),
+                }
+                let _ = writeln!(self.log.lock().unwrap(), "{line}");
+                output.push_str(line);
+                output.push('\n');
+            }
+            output
+        })
+    }
+}
 
 #[derive(Debug)]
 pub struct Macro {
     case_path: PathBuf,
     java_macro: PathBuf,
+    version: Option<String>,
+    force_version: bool,
+    license: Option<License>,
+    parallel: Parallel,
+    scheduler: Option<SchedulerKind>,
+    timeout: Option<Duration>,
+    prefix: bool,
+    log_dir: PathBuf,
+    output_dir: PathBuf,
+    destination: Option<PathBuf>,
+    artifacts: Vec<Artifact>,
+    retry: RetryPolicy,
 }
 impl Macro {
+    /// The paths are made absolute as StarCCM+ runs in its own working directory
     pub fn new(case_path: &Path, java_macro: impl Into<PathBuf>) -> Result<Self> {
         let java_macro: PathBuf = java_macro.into();
         if java_macro.is_file() {
             Ok(Self {
-                case_path: case_path.to_path_buf(),
-                java_macro,
+                case_path: absolute(case_path),
+                java_macro: absolute(&java_macro),
+                version: None,
+                force_version: false,
+                license: None,
+                parallel: Parallel::default(),
+                scheduler: None,
+                timeout: config().starccm.timeout.map(Duration::from_secs),
+                prefix: false,
+                log_dir: config().output.root.join("logs"),
+                output_dir: absolute(&config().output.root),
+                destination: None,
+                artifacts: vec![],
+                retry: config().retry.clone(),
             })
         } else {
             Err(MacroError::Java(java_macro))
         }
     }
-    pub fn play(self) -> Result<()> {
-        let output = Command::new(&*STARCCM)
-            .args([
+    /// Sets the StarCCM+ version, overriding the version pinned in the configuration
+    pub fn version(mut self, version: Option<String>) -> Self {
+        self.version = version;
+        self
+    }
+    /// Opens the sim with the default StarCCM+ binary if the pinned version is not installed
+    pub fn force_version(mut self, force: bool) -> Self {
+        self.force_version = force;
+        self
+    }
+    /// Sets the license, overriding the license of the configuration
+    pub fn license(mut self, license: License) -> Self {
+        self.license = Some(license);
+        self
+    }
+    /// Sets the parallel execution settings, StarCCM+ runs in serial by default
+    ///
+    /// The relative paths are resolved against the current directory, see [Parallel::absolute]
+    pub fn parallel(mut self, parallel: Parallel) -> Self {
+        self.parallel = parallel.absolute();
+        self
+    }
+    /// Sets the time after which StarCCM+ is killed, overriding the configuration timeout
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Prefixes the StarCCM+ output lines with the case name
+    pub fn prefix(mut self, prefix: bool) -> Self {
+        self.prefix = prefix;
+        self
+    }
+    /// Sets the directory of the StarCCM+ logs, `<output root>/logs` by default
+    ///
+    /// The log of each run is written to `<log dir>/<case>/<timestamp>_<macro>.log`
+    pub fn log_dir(mut self, log_dir: impl Into<PathBuf>) -> Self {
+        self.log_dir = log_dir.into();
+        self
+    }
+    /// Sets the value of the `{{output_dir}}` placeholder of the macro templates, `<output root>` by default
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = absolute(&output_dir.into());
+        self
+    }
+    /// Sets the files written by the macro, moved into `destination` after each successful run
+    ///
+    /// A run fails if any of its artifacts is missing
+    pub fn artifacts(mut self, destination: impl Into<PathBuf>, artifacts: Vec<Artifact>) -> Self {
+        self.destination = Some(destination.into());
+        self.artifacts = artifacts;
+        self
+    }
+    /// Sets the retry policy, overriding the configuration policy
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+    /// Submits the macro to a job scheduler instead of running it directly
+    pub fn scheduler(mut self, scheduler: Option<SchedulerKind>) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+    fn case(&self) -> String {
+        self.case_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+    /// Resolves the StarCCM+ binary
+    ///
+    /// The binary of the version pinned for the sim, or else of the version the sim was saved with,
+    /// is selected from the [Installations], and the default binary is used if it has that version.
+    /// The sim is refused if its version is not installed, unless [Macro::force_version] is set,
+    /// so that it is not silently upgraded by another version.
+    /// The default binary is used for an unpinned sim whose version cannot be read
+    pub fn starccm(&self) -> Result<PathBuf> {
+        self.resolve_starccm(true)
+    }
+    /// Resolves the StarCCM+ binary, running `starccm+ -version` if `probe` is set and the version is not in its path
+    ///
+    /// Without `probe`, a default binary of unknown version is selected, its version being checked when the macro is played
+    fn resolve_starccm(&self, probe: bool) -> Result<PathBuf> {
+        let config = config();
+        let binary = &config.starccm.binary;
+        let case = self.case();
+        let pinned = self
+            .version
+            .as_deref()
+            .or_else(|| config.starccm.pinned(&case));
+        let saved = match pinned {
+            Some(_) => None,
+            None => sim_version(&self.case_path).ok().flatten(),
+        };
+        let Some(pinned) = pinned.or(saved.as_deref()) else {
+            return Ok(binary.clone());
+        };
+        if let Some(installation) = Installations::new(config).find(pinned) {
+            return Ok(installation.binary.clone());
+        }
+        let found = binary_version(binary).or_else(|| {
+            probe
+                .then(|| starccm_version(binary, Duration::from_secs(60)).ok())
+                .flatten()
+                .flatten()
+        });
+        if found.is_none() && !probe {
+            return Ok(binary.clone());
+        }
+        if found.as_deref() == Some(pinned) {
+            return Ok(binary.clone());
+        }
+        let requires = if saved.is_some() {
+            "was saved with"
+        } else {
+            "is pinned to"
+        };
+        if self.force_version {
+            println!(
+                "{case} {requires} StarCCM+ {pinned} which is not installed, forcing {binary:?}"
+            );
+            return Ok(binary.clone());
+        }
+        Err(MacroError::Version {
+            case,
+            requires,
+            pinned: pinned.to_string(),
+            binary: binary.clone(),
+            found,
+        })
+    }
+    /// Name of a run of the macro: `<timestamp>_<macro>`
+    ///
+    /// A `-<n>` suffix is added if a log of a run with the same name already exists in `dir`
+    fn run_name(&self, dir: &Path) -> String {
+        let name = format!(
+            "{}_{}",
+            now(),
+            self.java_macro
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        );
+        (1..)
+            .map(|n| {
+                if n == 1 {
+                    name.clone()
+                } else {
+                    format!("{name}-{n}")
+                }
+            })
+            .find(|name| !dir.join(format!("{name}.log")).exists())
+            .unwrap()
+    }
+    fn resolved_license(&self) -> Result<License> {
+        Ok(match self.license.clone() {
+            Some(license) => license,
+            None => License::new(&config().license)?,
+        })
+    }
+    /// Analyzer of the StarCCM+ output of the macro
+    fn analyzer(&self) -> Result<LogAnalyzer> {
+        let config = config();
+        Ok(LogAnalyzer::new(&config.patterns)?
+            .zero_exit_failures(&config.starccm.zero_exit_failures)
+            .java_macro(&self.java_macro))
+    }
+    /// Values of the placeholders of the macro template
+    pub fn variables(&self) -> Variables {
+        Variables::new(&self.case(), &self.output_dir)
+    }
+    /// Working directory of a local run: `<temp dir>/cfdsim/<case>/<run>`
+    fn work_dir(&self, stem: &str) -> PathBuf {
+        env::temp_dir().join("cfdsim").join(self.case()).join(stem)
+    }
+    /// Renders the macro template into the working directory `dir`, returning the path to the macro to run
+    fn render(&self, dir: &Path) -> Result<Option<PathBuf>> {
+        Ok(match Template::read(&self.java_macro)? {
+            Some(template) => Some(template.render_to(&self.variables().work_dir(dir), dir)?),
+            None => None,
+        })
+    }
+    /// Artifacts with their placeholders replaced for a run in `work_dir`
+    fn rendered_artifacts(&self, work_dir: &Path) -> Result<Vec<Artifact>> {
+        let variables = self.variables().work_dir(work_dir);
+        self.artifacts
+            .iter()
+            .map(|artifact| {
+                artifact
+                    .render(&variables)
+                    .map_err(|placeholder| MacroError::ArtifactPlaceholder {
+                        case: self.case(),
+                        artifact: artifact.from.clone(),
+                        placeholder,
+                    })
+            })
+            .collect()
+    }
+    /// Moves the artifacts written in `work_dir` into the destination, adding them to the files of the `result`
+    fn collect(
+        &self,
+        mut result: MacroResult,
+        work_dir: &Path,
+        log: PathBuf,
+    ) -> Result<MacroResult> {
+        let Some(destination) = self.destination.as_deref() else {
+            return Ok(result);
+        };
+        let case = self.case();
+        let artifacts = self.rendered_artifacts(work_dir)?;
+        let files = collect_artifacts(&artifacts, &case, work_dir, destination)
+            .map_err(|source| MacroError::Artifact { case, source, log })?;
+        result.files.extend(files);
+        Ok(result)
+    }
+    /// StarCCM+ command running the `java` macro with the given license
+    fn build_command(&self, binary: &Path, license: &License, java: &Path) -> Command {
+        let mut command = Command::new(binary);
+        command
+            .arg("-batch")
+            .args(license.args())
+            .envs(license.envs())
+            .args(self.parallel.args())
+            .arg(java)
+            .arg(&self.case_path);
+        command
+    }
+    /// StarCCM+ command running the macro
+    ///
+    /// The command holds the license secret, use [Macro::dry_run] to display it.
+    /// A macro template is given as is, it is rendered when the macro is played
+    pub fn command(&self) -> Result<Command> {
+        Ok(self.build_command(
+            &self.starccm()?,
+            &self.resolved_license()?,
+            &self.java_macro,
+        ))
+    }
+    /// Description of the StarCCM+ run, with the license secret redacted
+    ///
+    /// Nothing is launched and no file is written:
+    /// the key given by `license.podkey_command` is shown as `<podkey_command>`
+    /// and the version of a StarCCM+ binary is only read from its path
+    pub fn dry_run(&self) -> Result<String> {
+        let license = match self.license.clone() {
+            Some(license) => license,
+            None => License::deferred(&config().license)?,
+        };
+        let command = self.build_command(&self.resolve_starccm(false)?, &license, &self.java_macro);
+        let case = self.case();
+        let quote = |s: &std::ffi::OsStr| {
+            let s = s.to_string_lossy();
+            if s.is_empty() || s.contains(char::is_whitespace) {
+                format!("{s:?}")
+            } else {
+                s.into_owned()
+            }
+        };
+        let mut lines = vec![
+            format!("{case}:"),
+            format!("  binary:  {:?}", command.get_program()),
+            format!(
+                "  command: {}",
+                license.redact(
+                    &std::iter::once(command.get_program())
+                        .chain(command.get_args())
+                        .map(quote)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )
+            ),
+        ];
+        for (key, value) in command.get_envs() {
+            lines.push(format!(
+                "  env:     {}={}",
+                key.to_string_lossy(),
+                license.redact(&value.map(quote).unwrap_or_default())
+            ));
+        }
+        let work_dir = match self.scheduler {
+            Some(_) => {
+                let dir = config().output.root.join("jobs").join(&case);
+                dir.join(self.run_name(&dir))
+            }
+            None => self.work_dir(&self.run_name(&self.log_dir.join(&case))),
+        };
+        lines.push(format!("  macro:   {:?}", self.java_macro));
+        if let Some(template) = Template::read(&self.java_macro)? {
+            let variables = self.variables().work_dir(&work_dir);
+            template.render(&variables)?;
+            for placeholder in template.placeholders() {
+                lines.push(format!(
+                    "  {{{{{placeholder}}}}} = {}",
+                    variables.get(placeholder).unwrap_or_default()
+                ));
+            }
+        }
+        lines.push(format!("  sim:     {:?}", self.case_path));
+        lines.push(format!("  workdir: {work_dir:?}"));
+        if let Some(destination) = self.destination.as_deref() {
+            for artifact in &self.rendered_artifacts(&work_dir)? {
+                lines.push(format!(
+                    "  artifact: {:?} -> {:?}",
+                    artifact.from,
+                    artifact
+                        .target(&case, destination)
+                        .unwrap_or_else(|| destination.join(artifact.to.as_deref().unwrap_or("")))
+                ));
+            }
+        }
+        match self.scheduler {
+            Some(kind) => lines.push(format!(
+                "  jobs:    {:?} ({kind})",
+                config().output.root.join("jobs").join(&case)
+            )),
+            None => lines.push(format!("  logs:    {:?}", self.log_dir.join(&case))),
+        }
+        Ok(lines.join("\n"))
+    }
+    /// StarCCM+ job running the macro
+    ///
+    /// The job script and log are written in `<output root>/jobs/<case>/`,
+    /// and the job runs in `<output root>/jobs/<case>/<run>/` where a macro template is rendered
+    pub fn job(&self, license: &License) -> Result<Job> {
+        let case = self.case();
+        let dir = config().output.root.join("jobs").join(&case);
+        let stem = self.run_name(&dir);
+        let work_dir = dir.join(&stem);
+        fs::create_dir_all(&work_dir)?;
+        let java = self.render(&work_dir)?;
+        let command = self.build_command(
+            &self.starccm()?,
+            license,
+            java.as_deref().unwrap_or(&self.java_macro),
+        );
+        Ok(Job {
+            name: case,
+            program: command.get_program().into(),
+            args: command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            envs: command
+                .get_envs()
+                .filter_map(|(k, v)| {
+                    Some((
+                        k.to_string_lossy().into_owned(),
+                        v?.to_string_lossy().into_owned(),
+                    ))
+                })
+                .collect(),
+            dir: work_dir,
+            ntasks: self.parallel.np,
+            script: dir.join(format!("{stem}.sh")),
+            log: dir.join(format!("{stem}.log")),
+        })
+    }
+    /// Submits the macro to the scheduler and waits for the job to finish
+    fn submit(&self, kind: SchedulerKind) -> Result<MacroResult> {
+        let config = config();
+        let scheduler = kind.scheduler(&config.scheduler);
+        let analyzer = self.analyzer()?;
+        let license = self.resolved_license()?;
+        let job = self.job(&license)?;
+        let id = scheduler.submit(&job)?;
+        println!("{}: submitted job {id} to {kind}", job.name);
+        let status = scheduler.wait(&id, Duration::from_secs(config.scheduler.poll))?;
+        // the job log is written by the scheduler, so it is redacted once the job is finished
+        let output = license.redact(&fs::read_to_string(&job.log).unwrap_or_default());
+        let _ = fs::write(&job.log, &output);
+        if status != JobStatus::Cancelled {
+            // the exit code of a job is not known, only whether it has completed
+            let outcome = analyzer.analyze(&output, (status == JobStatus::Completed).then_some(0));
+            if outcome.kind().is_some() {
+                return Err(MacroError::Outcome {
+                    case: job.name,
+                    outcome,
+                    log: job.log,
+                });
+            }
+        }
+        match status {
+            JobStatus::Completed => {
+                let result = MacroResult::parse(&output).map_err(|source| MacroError::Result {
+                    case: job.name,
+                    source,
+                    log: job.log.clone(),
+                })?;
+                self.collect(result, &job.dir, job.log)
+            }
+            JobStatus::Cancelled => Err(MacroError::Cancelled { case: job.name }),
+            status => Err(MacroError::Job {
+                case: job.name,
+                id,
+                status,
+                log: job.log,
+            }),
+        }
+    }
+    /// Runs the macro, retrying the transient failures according to the [RetryPolicy]
+    ///
+    /// Returns the data printed by the macro on the `@@cfdsim {...}` lines of its output
+    pub fn play(self) -> Result<MacroResult> {
+        let mut attempt = 1;
+        loop {
+            match self.attempt() {
+                Err(e) if self.retry.retries(&e, attempt) => {
+                    let delay = self.retry.delay(attempt);
+                    let message = format!(
+                        "attempt {attempt}/{} failed: {e}, retrying in {}s",
+                        self.retry.attempts,
+                        delay.as_secs()
+                    );
+                    eprintln!("{message}");
+                    if let Some(log) = e.log() {
+                        let _ = OpenOptions::new()
+                            .append(true)
+                            .open(log)
+                            .and_then(|mut log| writeln!(log, "cfdsim: {message}"));
+                    }
+                    let now = Instant::now();
+                    while now.elapsed() < delay {
+                        if is_cancelled() {
+                            return Err(MacroError::Cancelled { case: self.case() });
+                        }
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// Runs the macro once
+    fn attempt(&self) -> Result<MacroResult> {
+        if let Some(kind) = self.scheduler {
+            return self.submit(kind);
+        }
+        let case = self.case();
+        if is_cancelled() {
+            return Err(MacroError::Cancelled { case });
+        }
+        let license = self.resolved_license()?;
+        let analyzer = self.analyzer()?;
+        let dir = self.log_dir.join(&case);
+        let stem = self.run_name(&dir);
+        let log = dir.join(format!("{stem}.log"));
+        let work_dir = WorkDir(self.work_dir(&stem));
+        fs::create_dir_all(&work_dir.0)?;
+        let java = self.render(&work_dir.0)?;
+        let mut command = self.build_command(
+            &self.starccm()?,
+            &license,
+            java.as_deref().unwrap_or(&self.java_macro),
+        );
+        command
+            .current_dir(&work_dir.0)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // StarCCM+ and its server processes are put in their own process group to be killed together
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let tee = Tee {
+            prefix: self.prefix.then(|| case.clone()),
+            log: Arc::new(Mutex::new(
+                fs::create_dir_all(log.parent().unwrap())
+                    .and_then(|_| File::create(&log))
+                    .map_err(|e| MacroError::Log(log.clone(), e))?,
+            )),
+            license: license.clone(),
+        };
+        let mut child = command.spawn()?;
+        let _group = ProcessGroup::track(&child);
+        let stdout = tee.clone().stream(child.stdout.take(), false);
+        let stderr = tee.stream(child.stderr.take(), true);
+        let now = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => {}
+                Err(e) => {
+                    kill_group(&mut child, KILL_GRACE);
+                    break Err(MacroError::Command(e));
+                }
+            }
+            if is_cancelled() {
+                kill_group(&mut child, KILL_GRACE);
+                break Err(MacroError::Cancelled { case: case.clone() });
+            }
+            if let Some(timeout) = self.timeout
+                && now.elapsed() > timeout
+            {
+                kill_group(&mut child, KILL_GRACE);
+                break Err(MacroError::Timeout {
+                    case: case.clone(),
+                    timeout,
+                    log: log.clone(),
+                });
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+        // the pipes are closed once the process group has exited, so the tee threads end
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+        let status = status?;
+        // a last stdout line without a newline must not be merged with the first stderr line
+        let output = stdout + "\n" + &stderr;
+        match analyzer.analyze(&output, status.code()) {
+            MacroOutcome::Success => {
+                let result = MacroResult::parse(&output).map_err(|source| MacroError::Result {
+                    case,
+                    source,
+                    log: log.clone(),
+                })?;
+                self.collect(result, &work_dir.0, log)
+            }
+            outcome => Err(MacroError::Outcome { case, outcome, log }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        PodkeyVia, Secret,
+        testing::{self, TempDir},
+    };
+
+    /// Sim saved with StarCCM+ `version` and a java macro, written in `dir`
+    fn new_macro(dir: &Path, case: &str, version: Option<&str>) -> Macro {
+        testing::config();
+        let sim = dir.join(format!("{case}.sim"));
+        let header = match version {
+            Some(version) => format!("\0\0STAR-CCM+ {version} (linux-x86_64)\0\0"),
+            None => "\0\0\0".to_string(),
+        };
+        fs::write(&sim, header).unwrap();
+        let java = dir.join("update.java");
+        fs::write(&java, "").unwrap();
+        Macro::new(&sim, java).unwrap()
+    }
+
+    const DEFAULT: &str = "/opt/starccm/19.02.009/star/bin/starccm+";
+    const INSTALLED: &str = "/opt/starccm/17.06.007/star/bin/starccm+";
+
+    #[test]
+    fn binary_of_the_saved_version() {
+        let dir = TempDir::new("play_macro_saved");
+        let play = new_macro(&dir, "zen30az045_OS_7ms", Some("17.06.007"));
+        assert_eq!(play.starccm().unwrap(), Path::new(INSTALLED));
+        let play = new_macro(&dir, "zen30az045_OS_7ms", Some("19.02.009"));
+        assert_eq!(play.starccm().unwrap(), Path::new(DEFAULT));
+        // the default binary is used if the version cannot be read
+        let play = new_macro(&dir, "zen30az045_OS_7ms", None);
+        assert_eq!(play.starccm().unwrap(), Path::new(DEFAULT));
+    }
+
+    #[test]
+    fn refuse_a_saved_version_not_installed() {
+        let dir = TempDir::new("play_macro_refuse");
+        let play = new_macro(&dir, "zen30az045_OS_7ms", Some("18.04.008"));
+        assert!(matches!(
+            play.starccm(),
+            Err(MacroError::Version { requires: "was saved with", pinned, found: Some(found), .. })
+                if pinned == "18.04.008" && found == "19.02.009"
+        ));
+        let play = play.force_version(true);
+        assert_eq!(play.starccm().unwrap(), Path::new(DEFAULT));
+    }
+
+    #[test]
+    fn pinned_versions_override_the_saved_version() {
+        let dir = TempDir::new("play_macro_pinned");
+        let play = new_macro(&dir, "zen30az000_OS_7ms", Some("17.06.007"));
+        assert!(matches!(
+            play.starccm(),
+            Err(MacroError::Version { requires: "is pinned to", pinned, .. }) if pinned == "18.04.008"
+        ));
+        // e.g. --starccm-version or the version of a campaign
+        let play = play.version(Some("17.06.007".to_string()));
+        assert_eq!(play.starccm().unwrap(), Path::new(INSTALLED));
+        let play = new_macro(&dir, "zen30az045_OS_7ms", Some("18.04.008"))
+            .version(Some("19.02.009".to_string()));
+        assert_eq!(play.starccm().unwrap(), Path::new(DEFAULT));
+    }
+
+    #[test]
+    fn dry_run_redacts_the_key() {
+        let dir = TempDir::new("play_macro_dry_run");
+        for via in [PodkeyVia::Env, PodkeyVia::Args] {
+            let license = License::PowerOnDemand {
+                podkey: Secret::new("Xk93-secret-podkey"),
+                servers: vec!["1999@flex".to_string()],
+                via,
+            };
+            let text = new_macro(&dir, "zen30az045_OS_7ms", None)
+                .license(license)
+                .dry_run()
+                .unwrap();
+            assert!(!text.contains("Xk93-secret-podkey"), "{text}");
+            assert!(text.contains("********"), "{text}");
+        }
+    }
+
+    #[test]
+    fn command() {
+        let dir = TempDir::new("play_macro_command");
+        let play = new_macro(&dir, "zen30az045_OS_7ms", Some("17.06.007"))
+            .license(License::PowerOnDemand {
+                podkey: Secret::new("Xk93-secret-podkey"),
+                servers: vec!["1999@flex".to_string()],
+                via: PodkeyVia::Env,
+            })
+            .parallel(Parallel {
+                np: Some(8),
+                mpi: Some("openmpi".to_string()),
+                ..Parallel::default()
+            });
+        let command = play.command().unwrap();
+        assert_eq!(command.get_program(), INSTALLED);
+        let args: Vec<_> = command.get_args().collect();
+        let java = dir.join("update.java");
+        let sim = dir.join("zen30az045_OS_7ms.sim");
+        assert_eq!(
+            args,
+            [
                 "-batch",
                 "-power",
-                "-podkey",
-                &env::var("PODKEY")?,
                 "-licpath",
-                "1999@flex.cd-adapco.com",
-                self.java_macro.to_str().unwrap(),
-            ])
-            .arg(self.case_path)
-            .output()?;
-        let stdout = String::from_utf8(output.stdout)?;
-        if stdout.contains("Server process ended unexpectedly") {
-            return Err(MacroError::StdOut(stdout));
-        }
-        // let stderr = String::from_utf8(output.stderr)?;
-        // if !stderr.is_empty() {
-        //     return Err(MacroError::StdErr(stderr));
-        // }
-        Ok(())
+                "1999@flex",
+                "-np",
+                "8",
+                "-mpi",
+                "openmpi",
+                java.as_os_str().to_str().unwrap(),
+                sim.as_os_str().to_str().unwrap(),
+            ]
+        );
+        let envs: Vec<_> = command.get_envs().collect();
+        assert_eq!(
+            envs,
+            [(
+                std::ffi::OsStr::new("LM_PROJECT"),
+                Some(std::ffi::OsStr::new("Xk93-secret-podkey"))
+            )]
+        );
+    }
+
+    #[test]
+    fn dry_run_text() {
+        let dir = TempDir::new("play_macro_dry_run_text");
+        let text = new_macro(&dir, "zen30az045_OS_7ms", Some("17.06.007"))
+            .license(License::Server {
+                servers: vec!["1999@flex".to_string()],
+            })
+            .dry_run()
+            .unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[0], "zen30az045_OS_7ms:");
+        assert_eq!(lines[1], format!("  binary:  {INSTALLED:?}"));
+        assert_eq!(
+            lines[2],
+            format!(
+                "  command: {INSTALLED} -batch -licpath 1999@flex {} {}",
+                dir.join("update.java").display(),
+                dir.join("zen30az045_OS_7ms.sim").display()
+            )
+        );
     }
 }
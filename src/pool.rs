@@ -0,0 +1,126 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Mutex, mpsc},
+    thread,
+};
+
+/// Number of workers allowed for a `requested` number of concurrent jobs of `np` processes each
+///
+/// The number of workers is at least 1, the processes of all the workers fit in the CPUs of the machine
+/// and the workers do not use more than the `seats` licenses available
+pub fn workers(requested: usize, np: usize, seats: Option<usize>) -> usize {
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    limit(requested, np, cpus, seats)
+}
+
+fn limit(requested: usize, np: usize, cpus: usize, seats: Option<usize>) -> usize {
+    let np = np.max(1);
+    let mut n_worker = requested.max(1);
+    let by_cpus = (cpus / np).max(1);
+    if n_worker > by_cpus {
+        if np == 1 {
+            println!(
+                "limiting the number of concurrent jobs from {n_worker} to {by_cpus} (number of CPUs)"
+            );
+        } else {
+            println!(
+                "limiting the number of concurrent jobs from {n_worker} to {by_cpus} ({cpus} CPUs for {np} processes per job)"
+            );
+        }
+        n_worker = by_cpus;
+    }
+    if let Some(seats) = seats.map(|seats| seats.max(1))
+        && n_worker > seats
+    {
+        println!(
+            "limiting the number of concurrent jobs from {n_worker} to {seats} (license seats)"
+        );
+        n_worker = seats;
+    }
+    n_worker
+}
+
+/// Applies `work` to all the `items` with `n_worker` threads
+///
+/// The items are pulled from a shared queue as soon as a worker is available
+/// and `done` is called, in the calling thread, with the result of each item as it finishes
+pub fn run_concurrently<T, R, W, D>(n_worker: usize, items: Vec<T>, work: W, mut done: D)
+where
+    T: Send,
+    R: Send,
+    W: Fn(T) -> R + Sync,
+    D: FnMut(R),
+{
+    let queue = Mutex::new(items.into_iter());
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|s| {
+        for _ in 0..n_worker.max(1) {
+            let tx = tx.clone();
+            let queue = &queue;
+            let work = &work;
+            s.spawn(move || {
+                loop {
+                    let Some(item) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+                    if tx.send(work(item)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+        for result in rx {
+            done(result);
+        }
+    });
+}
+
+/// Runs `f`, turning a panic into an error so that a job cannot take the other jobs down
+pub fn catch_panic<R>(f: impl FnOnce() -> anyhow::Result<R>) -> anyhow::Result<R> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow::anyhow!("panicked: {message}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_workers() {
+        assert_eq!(limit(4, 1, 8, None), 4);
+        assert_eq!(limit(0, 1, 8, None), 1);
+        assert_eq!(limit(16, 1, 8, None), 8);
+        assert_eq!(limit(4, 4, 8, None), 2);
+        assert_eq!(limit(4, 16, 8, None), 1);
+        assert_eq!(limit(4, 1, 8, Some(3)), 3);
+        assert_eq!(limit(4, 4, 8, Some(1)), 1);
+        assert_eq!(limit(4, 1, 8, Some(0)), 1);
+    }
+
+    #[test]
+    fn run_all_items() {
+        let mut results = vec![];
+        run_concurrently(
+            3,
+            (0..10).collect(),
+            |i| i * i,
+            |square| results.push(square),
+        );
+        results.sort();
+        assert_eq!(results, (0..10).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn catch_panics() {
+        assert_eq!(catch_panic(|| Ok(1)).unwrap(), 1);
+        let error = catch_panic::<()>(|| panic!("broken sim")).unwrap_err();
+        assert_eq!(error.to_string(), "panicked: broken sim");
+    }
+}
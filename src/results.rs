@@ -0,0 +1,179 @@
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::timestamp::now;
+
+/// Checklist verdict of a case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+}
+impl From<bool> for Verdict {
+    fn from(pass: bool) -> Self {
+        if pass { Verdict::Pass } else { Verdict::Fail }
+    }
+}
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Pass => write!(f, "PASS"),
+            Verdict::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// Re-check modes of the cases with an existing checklist folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Recheck {
+    /// skips all the sims already checked
+    #[default]
+    None,
+    /// re-checks the sims that failed the checklist
+    Failed,
+    /// re-checks all the sims
+    All,
+}
+impl Recheck {
+    /// Checks if a case with the given `verdict` must be checked again
+    pub fn applies(self, verdict: Verdict) -> bool {
+        match self {
+            Recheck::None => false,
+            Recheck::Failed => verdict == Verdict::Fail,
+            Recheck::All => true,
+        }
+    }
+}
+
+/// Checklist folder of a case: `{case}@PASS` or `{case}@FAIL`
+///
+/// When a case is checked again, the content of the previous folder is archived
+/// into `history/{timestamp}@{verdict}` (`{timestamp}-N@{verdict}` for the next ones in the same second), the folder is renamed if the verdict has changed
+/// and the verdict is appended to `history.txt`
+#[derive(Debug, Clone)]
+pub struct CaseFolder {
+    root: PathBuf,
+    case: String,
+}
+impl CaseFolder {
+    pub fn new(root: impl Into<PathBuf>, case: impl Into<String>) -> Self {
+        Self {
+            root: root.into(),
+            case: case.into(),
+        }
+    }
+    /// Path to the folder for the given verdict
+    pub fn path(&self, verdict: Verdict) -> PathBuf {
+        self.root.join(format!("{}@{verdict}", self.case))
+    }
+    /// Returns the verdict of the existing folder, if any
+    pub fn verdict(&self) -> Option<Verdict> {
+        [Verdict::Pass, Verdict::Fail]
+            .into_iter()
+            .find(|&verdict| self.path(verdict).is_dir())
+    }
+    /// Archives the content of the existing folder and returns the (possibly renamed) folder for the new `verdict`
+    pub fn record(&self, verdict: Verdict) -> io::Result<PathBuf> {
+        let timestamp = now();
+        let path = self.path(verdict);
+        if let Some(previous) = self.verdict() {
+            let previous_path = self.path(previous);
+            let history = previous_path.join("history");
+            // the rechecks done in the same second are archived into distinct folders
+            let archive = (1..)
+                .map(|n| {
+                    history.join(if n == 1 {
+                        format!("{timestamp}@{previous}")
+                    } else {
+                        format!("{timestamp}-{n}@{previous}")
+                    })
+                })
+                .find(|archive| !archive.exists())
+                .unwrap();
+            println!("Archiving {previous_path:?} to {archive:?}");
+            archive_content(&previous_path, &archive)?;
+            // a folder of the other verdict left over by an interrupted run would make the rename fail
+            let stale = self.path(match previous {
+                Verdict::Pass => Verdict::Fail,
+                Verdict::Fail => Verdict::Pass,
+            });
+            if stale.exists() {
+                println!("Removing the stale {stale:?}");
+                fs::remove_dir_all(&stale)?;
+            }
+            if previous != verdict {
+                println!("Renaming {previous_path:?} to {path:?}");
+                fs::rename(&previous_path, &path)?;
+            }
+        }
+        fs::create_dir_all(&path)?;
+        let mut history = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.join("history.txt"))?;
+        writeln!(&mut history, "{timestamp} {verdict}")?;
+        Ok(path)
+    }
+}
+
+/// Moves all the entries of `folder`, but the history, into `archive`
+fn archive_content(folder: &Path, archive: &Path) -> io::Result<()> {
+    fs::create_dir_all(archive)?;
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == "history" || name == "history.txt" {
+            continue;
+        }
+        fs::rename(entry.path(), archive.join(name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn record_archives_each_recheck() {
+        let root = TempDir::new("results_record");
+        let folder = CaseFolder::new(&*root, "zen30az000_OS_7ms");
+        for verdict in [Verdict::Pass, Verdict::Pass, Verdict::Fail] {
+            let path = folder.record(verdict).unwrap();
+            fs::write(path.join("report.txt"), verdict.to_string()).unwrap();
+        }
+        assert_eq!(folder.verdict(), Some(Verdict::Fail));
+        let path = folder.path(Verdict::Fail);
+        let archives = fs::read_dir(path.join("history")).unwrap().count();
+        assert_eq!(archives, 2);
+        let history = fs::read_to_string(path.join("history.txt")).unwrap();
+        assert_eq!(history.lines().count(), 3);
+    }
+
+    #[test]
+    fn record_removes_the_stale_folder() {
+        let root = TempDir::new("results_stale");
+        let folder = CaseFolder::new(&*root, "zen30az000_OS_7ms");
+        for verdict in [Verdict::Pass, Verdict::Fail] {
+            fs::create_dir_all(folder.path(Verdict::Pass)).unwrap();
+            fs::create_dir_all(folder.path(Verdict::Fail)).unwrap();
+            fs::write(folder.path(Verdict::Fail).join("stale.txt"), "").unwrap();
+            let path = folder.record(verdict).unwrap();
+            assert_eq!(path, folder.path(verdict));
+            assert!(!path.join("stale.txt").exists());
+            assert_eq!(
+                fs::read_dir(&*root).unwrap().count(),
+                1,
+                "only the folder of the {verdict} verdict is left"
+            );
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FailureKind, MacroError};
+
+/// Retry policy of the StarCCM+ runs that fail for a transient reason
+///
+/// The delay before the n-th retry is `backoff * factor^(n-1)` seconds, up to `max_backoff` seconds
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Maximum number of runs, including the first one
+    pub attempts: usize,
+    /// Delay before the first retry, in seconds
+    pub backoff: u64,
+    /// Growth factor of the delay between two retries
+    pub factor: f64,
+    /// Maximum delay between two retries, in seconds
+    pub max_backoff: u64,
+    /// Classes of failures that are retried
+    pub transient: Vec<FailureKind>,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: 30,
+            factor: 2.,
+            max_backoff: 600,
+            transient: vec![FailureKind::License, FailureKind::ServerCrash],
+        }
+    }
+}
+impl RetryPolicy {
+    /// No retry
+    pub fn never() -> Self {
+        Self {
+            attempts: 1,
+            ..Default::default()
+        }
+    }
+    /// Checks if a run that has failed with `error` at the given `attempt` (starting at 1) must be retried
+    pub fn retries(&self, error: &MacroError, attempt: usize) -> bool {
+        attempt < self.attempts
+            && error
+                .outcome()
+                .and_then(|outcome| outcome.kind())
+                .is_some_and(|kind| self.transient.contains(&kind))
+    }
+    /// Delay before retrying a run that has failed at the given `attempt` (starting at 1)
+    ///
+    /// An undefined delay, e.g. with `backoff = 0` and `factor = inf`, is `max_backoff`
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let max = Duration::from_secs(self.max_backoff);
+        let delay = self.backoff as f64 * self.factor.powi(attempt.saturating_sub(1) as i32);
+        match Duration::try_from_secs_f64(delay) {
+            Ok(delay) => delay.min(max),
+            Err(_) if delay < 0. => Duration::ZERO,
+            Err(_) => max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(backoff: u64, factor: f64) -> RetryPolicy {
+        RetryPolicy {
+            backoff,
+            factor,
+            max_backoff: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn delay_grows_up_to_max_backoff() {
+        let policy = policy(10, 3.);
+        let delays: Vec<_> = (1..=4).map(|n| policy.delay(n).as_secs()).collect();
+        assert_eq!(delays, [10, 30, 90, 100]);
+        assert_eq!(policy.delay(0), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn delay_of_invalid_factors() {
+        assert_eq!(policy(10, f64::INFINITY).delay(2), Duration::from_secs(100));
+        assert_eq!(policy(0, f64::INFINITY).delay(2), Duration::from_secs(100));
+        assert_eq!(policy(10, f64::NAN).delay(2), Duration::from_secs(100));
+        assert_eq!(policy(10, -2.).delay(2), Duration::ZERO);
+        assert_eq!(policy(10, 1e300).delay(1000), Duration::from_secs(100));
+    }
+}
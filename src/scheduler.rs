@@ -0,0 +1,527 @@
+use std::{
+    fmt::{self, Display},
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ProcessGroup, SchedulerConfig, is_cancelled, kill_group};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error("failed to write the job script {0:?}")]
+    Script(PathBuf, #[source] io::Error),
+    #[error("failed to run `{0}`")]
+    Command(String, #[source] io::Error),
+    #[error("`{command}` failed ({status}): {stderr}")]
+    Failed {
+        command: String,
+        status: String,
+        stderr: String,
+    },
+    #[error("unexpected output of `{0}`: {1:?}")]
+    Parse(String, String),
+    #[error("unknown job {0}")]
+    UnknownJob(String),
+}
+type Result<T> = std::result::Result<T, SchedulerError>;
+
+/// Job schedulers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulerKind {
+    /// runs the jobs on the local machine
+    Local,
+    /// submits the jobs with `sbatch`
+    Slurm,
+    /// submits the jobs with `qsub`
+    Pbs,
+}
+impl SchedulerKind {
+    /// Builds the scheduler from the configuration
+    pub fn scheduler(self, config: &SchedulerConfig) -> Box<dyn Scheduler> {
+        match self {
+            SchedulerKind::Local => Box::new(Local::default()),
+            SchedulerKind::Slurm => Box::new(Slurm::new(config)),
+            SchedulerKind::Pbs => Box::new(Pbs::new(config)),
+        }
+    }
+}
+impl Display for SchedulerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerKind::Local => write!(f, "local"),
+            SchedulerKind::Slurm => write!(f, "slurm"),
+            SchedulerKind::Pbs => write!(f, "pbs"),
+        }
+    }
+}
+
+/// A StarCCM+ run submitted to a [Scheduler]
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// Job name
+    pub name: String,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// Environment of the job, that may hold secrets
+    pub envs: Vec<(String, String)>,
+    /// Working directory of the job
+    pub dir: PathBuf,
+    /// Number of processes
+    pub ntasks: Option<usize>,
+    /// Job script
+    pub script: PathBuf,
+    /// Job log, with both stdout and stderr
+    pub log: PathBuf,
+}
+impl Job {
+    /// Shell command of the job
+    fn command_line(&self) -> String {
+        Some(self.program.to_string_lossy().into_owned())
+            .into_iter()
+            .chain(self.args.iter().cloned())
+            .map(|arg| quote(&arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    /// Writes the job script with the scheduler `directives`
+    ///
+    /// The environment is exported by the script rather than by the submit command,
+    /// so the license key is not stored with the job attributes (`qsub -V`),
+    /// and the script is readable only by its owner
+    fn write_script(&self, directives: &[String]) -> Result<()> {
+        let error = |e| SchedulerError::Script(self.script.clone(), e);
+        if let Some(dir) = self.script.parent() {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        let mut script = vec!["#!/bin/bash".to_string()];
+        script.extend(directives.iter().cloned());
+        script.extend(
+            self.envs
+                .iter()
+                .map(|(key, value)| format!("export {key}={}", quote(value))),
+        );
+        script.push(format!("cd {}", quote(&self.dir.to_string_lossy())));
+        script.push(format!("exec {}", self.command_line()));
+        let _ = fs::remove_file(&self.script);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o700);
+        options
+            .open(&self.script)
+            .and_then(|mut file| file.write_all((script.join("\n") + "\n").as_bytes()))
+            .map_err(error)
+    }
+}
+
+/// Quotes `arg` for the shell
+fn quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@+,".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Status of a job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    /// the job has failed, with the reason given by the scheduler
+    Failed(String),
+    Cancelled,
+}
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed(_) | JobStatus::Cancelled
+        )
+    }
+}
+impl Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobStatus::Pending => write!(f, "PENDING"),
+            JobStatus::Running => write!(f, "RUNNING"),
+            JobStatus::Completed => write!(f, "COMPLETED"),
+            JobStatus::Failed(reason) => write!(f, "FAILED ({reason})"),
+            JobStatus::Cancelled => write!(f, "CANCELLED"),
+        }
+    }
+}
+
+/// Job scheduler
+pub trait Scheduler: Send + Sync {
+    /// Writes the job script, submits it and returns the job id
+    fn submit(&self, job: &Job) -> Result<String>;
+    /// Status of a submitted job
+    fn status(&self, id: &str) -> Result<JobStatus>;
+    /// Cancels a submitted job
+    fn cancel(&self, id: &str) -> Result<()>;
+    /// Polls the status of a job every `poll` until it is finished
+    ///
+    /// The job is cancelled if the StarCCM+ runs are cancelled while waiting
+    fn wait(&self, id: &str, poll: Duration) -> Result<JobStatus> {
+        loop {
+            let status = self.status(id)?;
+            if status.is_finished() {
+                return Ok(status);
+            }
+            let now = Instant::now();
+            while now.elapsed() < poll {
+                if is_cancelled() {
+                    self.cancel(id)?;
+                    return Ok(JobStatus::Cancelled);
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+}
+
+/// Runs `command` and returns its stdout
+fn run(mut command: Command) -> Result<String> {
+    let line = format!("{command:?}");
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| SchedulerError::Command(line.clone(), e))?;
+    if !output.status.success() {
+        return Err(SchedulerError::Failed {
+            command: line,
+            status: output.status.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs the jobs on the local machine, the job id being the process id
+#[derive(Debug, Default)]
+pub struct Local {
+    children: Mutex<Vec<(String, Child, ProcessGroup)>>,
+}
+impl Scheduler for Local {
+    fn submit(&self, job: &Job) -> Result<String> {
+        job.write_script(&[])?;
+        let log = File::create(&job.log).map_err(|e| SchedulerError::Script(job.log.clone(), e))?;
+        let err = log
+            .try_clone()
+            .map_err(|e| SchedulerError::Script(job.log.clone(), e))?;
+        let mut command = Command::new("bash");
+        command
+            .arg(&job.script)
+            .stdin(Stdio::null())
+            .stdout(log)
+            .stderr(err);
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let child = command
+            .spawn()
+            .map_err(|e| SchedulerError::Command(format!("bash {:?}", job.script), e))?;
+        let id = child.id().to_string();
+        let group = ProcessGroup::track(&child);
+        self.children
+            .lock()
+            .unwrap()
+            .push((id.clone(), child, group));
+        Ok(id)
+    }
+    /// Status of a job, the job being forgotten once it is finished
+    fn status(&self, id: &str) -> Result<JobStatus> {
+        let mut children = self.children.lock().unwrap();
+        let i = children
+            .iter()
+            .position(|(child_id, _, _)| child_id == id)
+            .ok_or_else(|| SchedulerError::UnknownJob(id.to_string()))?;
+        let status = match children[i]
+            .1
+            .try_wait()
+            .map_err(|e| SchedulerError::Command(format!("wait {id}"), e))?
+        {
+            None => return Ok(JobStatus::Running),
+            Some(status) if status.success() => JobStatus::Completed,
+            Some(status) => JobStatus::Failed(status.to_string()),
+        };
+        children.swap_remove(i);
+        Ok(status)
+    }
+    fn cancel(&self, id: &str) -> Result<()> {
+        let mut children = self.children.lock().unwrap();
+        let (_, child, _) = children
+            .iter_mut()
+            .find(|(child_id, _, _)| child_id == id)
+            .ok_or_else(|| SchedulerError::UnknownJob(id.to_string()))?;
+        kill_group(child, Duration::from_secs(10));
+        Ok(())
+    }
+}
+
+/// Submits the jobs to Slurm with `sbatch` and polls them with `squeue` and `sacct`
+#[derive(Debug)]
+pub struct Slurm {
+    sbatch: PathBuf,
+    squeue: PathBuf,
+    sacct: PathBuf,
+    scancel: PathBuf,
+    directives: Vec<String>,
+}
+impl Slurm {
+    pub fn new(config: &SchedulerConfig) -> Self {
+        Self {
+            sbatch: config.sbatch.clone(),
+            squeue: config.squeue.clone(),
+            sacct: config.sacct.clone(),
+            scancel: config.scancel.clone(),
+            directives: config.directives.clone(),
+        }
+    }
+}
+impl Scheduler for Slurm {
+    fn submit(&self, job: &Job) -> Result<String> {
+        let mut directives = vec![
+            format!("#SBATCH --job-name={}", quote(&job.name)),
+            format!("#SBATCH --output={}", quote(&job.log.to_string_lossy())),
+        ];
+        if let Some(ntasks) = job.ntasks {
+            directives.push(format!("#SBATCH --ntasks={ntasks}"));
+        }
+        directives.extend(self.directives.iter().map(|d| format!("#SBATCH {d}")));
+        job.write_script(&directives)?;
+        let mut command = Command::new(&self.sbatch);
+        // sbatch exports its environment to the job
+        command
+            .arg("--parsable")
+            .arg(&job.script)
+            .env_remove("PODKEY");
+        let stdout = run(command)?;
+        // --parsable prints `jobid[;cluster]`
+        stdout
+            .trim()
+            .split(';')
+            .next()
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| SchedulerError::Parse(self.sbatch.display().to_string(), stdout))
+    }
+    fn status(&self, id: &str) -> Result<JobStatus> {
+        let mut command = Command::new(&self.squeue);
+        command.args(["-h", "-j", id, "-o", "%T"]);
+        let state = match run(command) {
+            Ok(stdout) if !stdout.trim().is_empty() => stdout.trim().to_string(),
+            // jobs no longer in the queue are looked up in the accounting
+            _ => {
+                let mut command = Command::new(&self.sacct);
+                command.args(["-n", "-X", "-P", "-j", id, "-o", "State"]);
+                let stdout = run(command)?;
+                stdout
+                    .split_whitespace()
+                    .next()
+                    .map(str::to_string)
+                    .ok_or_else(|| SchedulerError::UnknownJob(id.to_string()))?
+            }
+        };
+        Ok(match state.as_str() {
+            "PENDING" | "CONFIGURING" | "REQUEUED" | "SUSPENDED" => JobStatus::Pending,
+            "RUNNING" | "COMPLETING" => JobStatus::Running,
+            "COMPLETED" => JobStatus::Completed,
+            "CANCELLED" => JobStatus::Cancelled,
+            _ => JobStatus::Failed(state),
+        })
+    }
+    fn cancel(&self, id: &str) -> Result<()> {
+        let mut command = Command::new(&self.scancel);
+        command.arg(id);
+        run(command).map(|_| ())
+    }
+}
+
+/// Submits the jobs to PBS with `qsub` and polls them with `qstat`
+#[derive(Debug)]
+pub struct Pbs {
+    qsub: PathBuf,
+    qstat: PathBuf,
+    qdel: PathBuf,
+    directives: Vec<String>,
+}
+impl Pbs {
+    pub fn new(config: &SchedulerConfig) -> Self {
+        Self {
+            qsub: config.qsub.clone(),
+            qstat: config.qstat.clone(),
+            qdel: config.qdel.clone(),
+            directives: config.directives.clone(),
+        }
+    }
+}
+impl Scheduler for Pbs {
+    fn submit(&self, job: &Job) -> Result<String> {
+        // PBS job names are limited to 15 characters
+        let name: String = job.name.chars().take(15).collect();
+        let mut directives = vec![
+            format!("#PBS -N {}", quote(&name)),
+            "#PBS -j oe".to_string(),
+            format!("#PBS -o {}", quote(&job.log.to_string_lossy())),
+        ];
+        if let Some(ntasks) = job.ntasks {
+            directives.push(format!("#PBS -l select=1:ncpus={ntasks}:mpiprocs={ntasks}"));
+        }
+        directives.extend(self.directives.iter().map(|d| format!("#PBS {d}")));
+        job.write_script(&directives)?;
+        let mut command = Command::new(&self.qsub);
+        command.arg(&job.script);
+        let stdout = run(command)?;
+        let id = stdout.trim();
+        if id.is_empty() {
+            Err(SchedulerError::Parse(
+                self.qsub.display().to_string(),
+                stdout,
+            ))
+        } else {
+            Ok(id.to_string())
+        }
+    }
+    fn status(&self, id: &str) -> Result<JobStatus> {
+        let mut command = Command::new(&self.qstat);
+        command.args(["-x", "-f", id]);
+        let stdout = run(command)?;
+        let attribute = |name: &str| {
+            stdout.lines().find_map(|line| {
+                line.trim()
+                    .strip_prefix(name)
+                    .and_then(|rest| rest.trim_start().strip_prefix('='))
+                    .map(|value| value.trim().to_string())
+            })
+        };
+        let state = attribute("job_state").ok_or_else(|| {
+            SchedulerError::Parse(self.qstat.display().to_string(), stdout.clone())
+        })?;
+        Ok(match state.as_str() {
+            "Q" | "H" | "W" | "T" => JobStatus::Pending,
+            "R" | "E" | "B" => JobStatus::Running,
+            "F" | "C" => match attribute("Exit_status").as_deref() {
+                Some("0") => JobStatus::Completed,
+                Some(status) => JobStatus::Failed(format!("exit status {status}")),
+                None => JobStatus::Failed("unknown exit status".to_string()),
+            },
+            _ => JobStatus::Failed(format!("job state {state}")),
+        })
+    }
+    fn cancel(&self, id: &str) -> Result<()> {
+        let mut command = Command::new(&self.qdel);
+        command.arg(id);
+        run(command).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn stub(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/stubs")
+            .join(name)
+    }
+
+    fn stub_config() -> SchedulerConfig {
+        SchedulerConfig {
+            sbatch: stub("sbatch"),
+            squeue: stub("squeue"),
+            sacct: stub("sacct"),
+            scancel: stub("scancel"),
+            qsub: stub("qsub"),
+            qstat: stub("qstat"),
+            qdel: stub("qdel"),
+            ..SchedulerConfig::default()
+        }
+    }
+
+    /// Job printing its license key and exiting with `code`, written in `dir`
+    fn job(dir: &Path, code: i32) -> Job {
+        fs::create_dir_all(dir.join("run")).unwrap();
+        Job {
+            name: "job".to_string(),
+            program: PathBuf::from("sh"),
+            args: vec![
+                "-c".to_string(),
+                format!("echo key=$LM_PROJECT in $(basename $PWD); exit {code}"),
+            ],
+            envs: vec![("LM_PROJECT".to_string(), "secret key".to_string())],
+            dir: dir.join("run"),
+            ntasks: Some(4),
+            script: dir.join("job.sh"),
+            log: dir.join("job.log"),
+        }
+    }
+
+    fn run_job(kind: SchedulerKind, job: &Job) -> JobStatus {
+        let scheduler = kind.scheduler(&stub_config());
+        let id = scheduler.submit(job).unwrap();
+        scheduler.wait(&id, Duration::ZERO).unwrap()
+    }
+
+    #[test]
+    fn slurm_stub() {
+        let dir = TempDir::new("scheduler_slurm");
+        let job = job(&dir, 0);
+        assert_eq!(run_job(SchedulerKind::Slurm, &job), JobStatus::Completed);
+        let log = fs::read_to_string(&job.log).unwrap();
+        assert_eq!(log.trim(), "key=secret key in run");
+        let script = fs::read_to_string(&job.script).unwrap();
+        assert!(script.contains("#SBATCH --ntasks=4\n"));
+        assert!(script.contains("export LM_PROJECT='secret key'\n"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&job.script).unwrap().permissions().mode();
+            assert_eq!(mode & 0o077, 0);
+        }
+    }
+
+    #[test]
+    fn pbs_stub() {
+        let dir = TempDir::new("scheduler_pbs");
+        let job = job(&dir, 3);
+        assert_eq!(
+            run_job(SchedulerKind::Pbs, &job),
+            JobStatus::Failed("exit status 3".to_string())
+        );
+        let log = fs::read_to_string(&job.log).unwrap();
+        assert_eq!(log.trim(), "key=secret key in run");
+    }
+
+    #[test]
+    fn local() {
+        let dir = TempDir::new("scheduler_local");
+        let job = job(&dir, 0);
+        let scheduler = Local::default();
+        let id = scheduler.submit(&job).unwrap();
+        assert_eq!(
+            scheduler.wait(&id, Duration::ZERO).unwrap(),
+            JobStatus::Completed
+        );
+        // the finished jobs are forgotten
+        assert!(scheduler.children.lock().unwrap().is_empty());
+        let log = fs::read_to_string(&job.log).unwrap();
+        assert_eq!(log.trim(), "key=secret key in run");
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::Case;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SelectionError {
+    #[error("invalid glob pattern {0:?}")]
+    Glob(String, #[source] glob::PatternError),
+    #[error("invalid regular expression {0:?}")]
+    Regex(String, #[source] regex::Error),
+}
+
+/// File name pattern
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(glob::Pattern),
+    Regex(Regex),
+}
+impl Matcher {
+    fn is_match(&self, name: &str) -> bool {
+        match self {
+            Matcher::Glob(pattern) => pattern.matches(name),
+            Matcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Selection of the sims of a directory
+///
+/// A file is selected if its name matches one of the include patterns (`*.sim` if none is given)
+/// and none of the exclude patterns, and if the case parsed from its name has one of the given zenith angles,
+/// azimuth angles and configurations
+#[derive(Debug, Clone, Default)]
+pub struct SimFilter {
+    include: Vec<Matcher>,
+    exclude: Vec<Matcher>,
+    zen: Vec<u32>,
+    az: Vec<u32>,
+    config: Vec<String>,
+}
+impl SimFilter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    fn glob(pattern: &str) -> Result<Matcher, SelectionError> {
+        glob::Pattern::new(pattern)
+            .map(Matcher::Glob)
+            .map_err(|e| SelectionError::Glob(pattern.to_string(), e))
+    }
+    fn regex(regex: &str) -> Result<Matcher, SelectionError> {
+        Regex::new(regex)
+            .map(Matcher::Regex)
+            .map_err(|e| SelectionError::Regex(regex.to_string(), e))
+    }
+    /// Selects the files with a name matching the glob `pattern`
+    pub fn include(mut self, pattern: &str) -> Result<Self, SelectionError> {
+        self.include.push(Self::glob(pattern)?);
+        Ok(self)
+    }
+    /// Rejects the files with a name matching the glob `pattern`
+    pub fn exclude(mut self, pattern: &str) -> Result<Self, SelectionError> {
+        self.exclude.push(Self::glob(pattern)?);
+        Ok(self)
+    }
+    /// Selects the files with a name matching the regular expression
+    pub fn include_regex(mut self, regex: &str) -> Result<Self, SelectionError> {
+        self.include.push(Self::regex(regex)?);
+        Ok(self)
+    }
+    /// Rejects the files with a name matching the regular expression
+    pub fn exclude_regex(mut self, regex: &str) -> Result<Self, SelectionError> {
+        self.exclude.push(Self::regex(regex)?);
+        Ok(self)
+    }
+    /// Selects the cases with one of the zenith angles
+    pub fn zen(mut self, zen: impl IntoIterator<Item = u32>) -> Self {
+        self.zen.extend(zen);
+        self
+    }
+    /// Selects the cases with one of the azimuth angles
+    pub fn az(mut self, az: impl IntoIterator<Item = u32>) -> Self {
+        self.az.extend(az);
+        self
+    }
+    /// Selects the cases with one of the configurations
+    pub fn config(mut self, config: impl IntoIterator<Item = String>) -> Self {
+        self.config.extend(config);
+        self
+    }
+    /// Checks if the file is selected
+    pub fn matches(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+            return false;
+        };
+        let included = if self.include.is_empty() {
+            path.extension().is_some_and(|ext| ext == "sim")
+        } else {
+            self.include.iter().any(|m| m.is_match(&name))
+        };
+        if !included || self.exclude.iter().any(|m| m.is_match(&name)) {
+            return false;
+        }
+        if self.zen.is_empty() && self.az.is_empty() && self.config.is_empty() {
+            return true;
+        }
+        let Some(case) = path
+            .file_stem()
+            .and_then(|stem| Case::parse(&stem.to_string_lossy()))
+        else {
+            return false;
+        };
+        (self.zen.is_empty() || self.zen.contains(&case.zen()))
+            && (self.az.is_empty() || self.az.contains(&case.az()))
+            && (self.config.is_empty() || self.config.iter().any(|c| c == case.config()))
+    }
+    /// Selected files of `dir`, and of its sub-directories if `recursive`, sorted by path
+    pub fn select(&self, dir: &Path, recursive: bool) -> io::Result<Vec<PathBuf>> {
+        let mut sims = vec![];
+        self.collect(dir, recursive, &mut sims)?;
+        sims.sort();
+        Ok(sims)
+    }
+    fn collect(&self, dir: &Path, recursive: bool, sims: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if recursive {
+                    self.collect(&path, recursive, sims)?;
+                }
+            } else if self.matches(&path) {
+                sims.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn selected(filter: &SimFilter, names: &[&'static str]) -> Vec<&'static str> {
+        names
+            .iter()
+            .copied()
+            .filter(|name| filter.matches(Path::new(name)))
+            .collect()
+    }
+
+    const NAMES: [&str; 5] = [
+        "zen30az000_OS_7ms.sim",
+        "zen30az090_OS_7ms.sim",
+        "zen60az000_CS_7ms.sim",
+        "zen60az000_CS_7ms.sim~",
+        "zen30az000_OS_7ms",
+    ];
+
+    #[test]
+    fn include_and_exclude_globs() {
+        assert_eq!(
+            selected(&SimFilter::new(), &NAMES),
+            [
+                "zen30az000_OS_7ms.sim",
+                "zen30az090_OS_7ms.sim",
+                "zen60az000_CS_7ms.sim"
+            ]
+        );
+        let filter = SimFilter::new()
+            .include("zen30*")
+            .unwrap()
+            .exclude("*az090*")
+            .unwrap();
+        assert_eq!(
+            selected(&filter, &NAMES),
+            ["zen30az000_OS_7ms.sim", "zen30az000_OS_7ms"]
+        );
+        let filter = SimFilter::new().include_regex(r"_CS_\d+ms\.sim$").unwrap();
+        assert_eq!(selected(&filter, &NAMES), ["zen60az000_CS_7ms.sim"]);
+        assert!(matches!(
+            SimFilter::new().include("[zen"),
+            Err(SelectionError::Glob(..))
+        ));
+    }
+
+    #[test]
+    fn extension_less_files() {
+        let filter = SimFilter::new().zen([30]).az([0]);
+        assert!(!filter.matches(Path::new("zen30az000_OS_7ms")));
+        let filter = filter.include("*").unwrap();
+        assert!(filter.matches(Path::new("zen30az000_OS_7ms")));
+        assert!(!filter.matches(Path::new("zen30az090_OS_7ms")));
+    }
+
+    #[test]
+    fn case_attributes() {
+        let filter = SimFilter::new().zen([30]);
+        assert_eq!(
+            selected(&filter, &NAMES),
+            ["zen30az000_OS_7ms.sim", "zen30az090_OS_7ms.sim"]
+        );
+        let filter = SimFilter::new().az([0]);
+        assert_eq!(
+            selected(&filter, &NAMES),
+            ["zen30az000_OS_7ms.sim", "zen60az000_CS_7ms.sim"]
+        );
+        let filter = SimFilter::new().config(["CS".to_string()]);
+        assert_eq!(selected(&filter, &NAMES), ["zen60az000_CS_7ms.sim"]);
+        let filter = SimFilter::new()
+            .zen([30, 60])
+            .az([0])
+            .config(["OS".to_string()]);
+        assert_eq!(selected(&filter, &NAMES), ["zen30az000_OS_7ms.sim"]);
+        // the files whose name is not a case are rejected by the case filters
+        assert!(SimFilter::new().matches(Path::new("mesh.sim")));
+        assert!(!SimFilter::new().zen([30]).matches(Path::new("mesh.sim")));
+    }
+
+    #[test]
+    fn select_recursively() {
+        let dir = TempDir::new("selection_select");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in [
+            "zen30az000_OS_7ms.sim",
+            "notes.txt",
+            "sub/zen60az000_CS_7ms.sim",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let filter = SimFilter::new();
+        assert_eq!(
+            filter.select(&dir, false).unwrap(),
+            [dir.join("zen30az000_OS_7ms.sim")]
+        );
+        assert_eq!(
+            filter.select(&dir, true).unwrap(),
+            [
+                dir.join("sub/zen60az000_CS_7ms.sim"),
+                dir.join("zen30az000_OS_7ms.sim")
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use regex::{Captures, Regex};
+
+use crate::Case;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("failed to read the java macro {0:?}")]
+    Read(PathBuf, #[source] io::Error),
+    #[error("failed to write the rendered java macro {0:?}")]
+    Write(PathBuf, #[source] io::Error),
+    #[error(
+        "the java macro {path:?} uses {{{{{placeholder}}}}} but the case name {case:?} does not give it (expected zen<zen>az<az>_<config>_<wind speed>ms)"
+    )]
+    Missing {
+        path: PathBuf,
+        placeholder: String,
+        case: String,
+    },
+}
+
+/// Placeholders of the java macro templates
+pub const PLACEHOLDERS: [&str; 7] = [
+    "case",
+    "output_dir",
+    "work_dir",
+    "zen",
+    "az",
+    "config",
+    "wind_speed",
+];
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(r"\{{\{{\s*({})\s*\}}\}}", PLACEHOLDERS.join("|"))).unwrap()
+});
+
+/// Values of the placeholders for a case
+///
+/// `zen`, `az`, `config` and `wind_speed` are parsed from the case name,
+/// and `work_dir` is only given for the runs of a macro
+#[derive(Debug, Clone)]
+pub struct Variables {
+    case: String,
+    values: BTreeMap<&'static str, String>,
+}
+impl Variables {
+    pub fn new(case: &str, output_dir: &Path) -> Self {
+        let mut values = BTreeMap::from([
+            ("case", case.to_string()),
+            ("output_dir", output_dir.to_string_lossy().into_owned()),
+        ]);
+        if let Some(parsed) = Case::parse(case) {
+            values.insert("zen", parsed.zen().to_string());
+            values.insert("az", parsed.az().to_string());
+            values.insert("config", parsed.config().to_string());
+            if let Some(wind_speed) = parsed.wind_speed() {
+                values.insert("wind_speed", wind_speed.to_string());
+            }
+        }
+        Self {
+            case: case.to_string(),
+            values,
+        }
+    }
+    /// Sets the working directory of the macro run
+    pub fn work_dir(mut self, work_dir: &Path) -> Self {
+        self.values
+            .insert("work_dir", work_dir.to_string_lossy().into_owned());
+        self
+    }
+    /// Value of a placeholder
+    pub fn get(&self, placeholder: &str) -> Option<&str> {
+        self.values.get(placeholder).map(String::as_str)
+    }
+    /// Replaces the placeholders of `text` with their values
+    ///
+    /// Returns the first placeholder without a value as error
+    pub fn render(&self, text: &str) -> Result<String, String> {
+        if let Some(placeholder) = PLACEHOLDER
+            .captures_iter(text)
+            .map(|caps| caps[1].to_string())
+            .find(|placeholder| self.get(placeholder).is_none())
+        {
+            return Err(placeholder);
+        }
+        Ok(PLACEHOLDER
+            .replace_all(text, |caps: &Captures| {
+                self.get(&caps[1]).unwrap_or_default().to_string()
+            })
+            .into_owned())
+    }
+}
+
+/// Java macro with `{{placeholder}}`s rendered per case
+#[derive(Debug, Clone)]
+pub struct Template {
+    path: PathBuf,
+    source: String,
+}
+impl Template {
+    /// Reads a java macro, returning `None` if it has no placeholder
+    pub fn read(path: &Path) -> Result<Option<Self>, TemplateError> {
+        let source =
+            fs::read_to_string(path).map_err(|e| TemplateError::Read(path.to_path_buf(), e))?;
+        Ok(PLACEHOLDER.is_match(&source).then(|| Self {
+            path: path.to_path_buf(),
+            source,
+        }))
+    }
+    /// Placeholders used by the macro
+    pub fn placeholders(&self) -> BTreeSet<&str> {
+        PLACEHOLDER
+            .captures_iter(&self.source)
+            .filter_map(|caps| caps.get(1))
+            .map(|m| m.as_str())
+            .collect()
+    }
+    /// Replaces the placeholders with their values
+    pub fn render(&self, variables: &Variables) -> Result<String, TemplateError> {
+        variables
+            .render(&self.source)
+            .map_err(|placeholder| TemplateError::Missing {
+                path: self.path.clone(),
+                placeholder,
+                case: variables.case.clone(),
+            })
+    }
+    /// Renders the macro into `dir`, keeping the file name as StarCCM+ expects it to match the macro class name
+    pub fn render_to(&self, variables: &Variables, dir: &Path) -> Result<PathBuf, TemplateError> {
+        let source = self.render(variables)?;
+        let path = dir.join(self.path.file_name().unwrap_or_default());
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&path, source))
+            .map_err(|e| TemplateError::Write(path.clone(), e))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_the_case_placeholders() {
+        let variables = Variables::new("zen30az045_OS_7ms", Path::new("/out"));
+        assert_eq!(
+            variables
+                .render("{{case}}: zen={{zen}} az={{ az }} {{config}} {{wind_speed}}m/s in {{output_dir}}")
+                .unwrap(),
+            "zen30az045_OS_7ms: zen=30 az=45 OS 7m/s in /out"
+        );
+        // unknown placeholders are left as is
+        assert_eq!(variables.render("{{other}}").unwrap(), "{{other}}");
+    }
+
+    #[test]
+    fn render_missing_placeholders() {
+        let variables = Variables::new("sim_force_update", Path::new("/out"));
+        assert_eq!(variables.render("{{case}} {{zen}}"), Err("zen".to_string()));
+        assert_eq!(
+            variables.render("{{work_dir}}"),
+            Err("work_dir".to_string())
+        );
+        let variables = variables.work_dir(Path::new("/tmp/run"));
+        assert_eq!(
+            variables.render("{{work_dir}}/a.sim").unwrap(),
+            "/tmp/run/a.sim"
+        );
+    }
+}
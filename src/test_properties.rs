@@ -80,12 +80,11 @@ impl<'a> TestProperty<'a> {
             loop {
                 match reader.read_event_into(&mut buf)? {
                     Event::Start(e) => {
-                        if e.name().as_ref() == event {
-                            if let Ok(Some(attr)) = e.try_get_attribute("PresentationName") {
-                                if attr.value.as_ref() == prop.to_string().into_bytes() {
-                                    break;
-                                }
-                            }
+                        if e.name().as_ref() == event
+                            && let Ok(Some(attr)) = e.try_get_attribute("PresentationName")
+                            && attr.value.as_ref() == prop.to_string().into_bytes()
+                        {
+                            break;
                         }
                     }
                     Event::Eof => {
@@ -100,11 +99,11 @@ impl<'a> TestProperty<'a> {
         let val = loop {
             match reader.read_event_into(&mut buf)? {
                 Event::Start(e) => {
-                    if e.name().as_ref() == self.sub_event {
-                        if let Event::Text(t) = reader.read_event_into(&mut buf)? {
-                            let txt = t.unescape().unwrap().into_owned();
-                            break txt;
-                        }
+                    if e.name().as_ref() == self.sub_event
+                        && let Event::Text(t) = reader.read_event_into(&mut buf)?
+                    {
+                        let txt = t.unescape().unwrap().into_owned();
+                        break txt;
                     }
                 }
                 Event::Eof => {
@@ -0,0 +1,53 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use crate::{Config, LicenseMode};
+
+/// Directory of a unit test, removed when dropped even if the test fails
+pub struct TempDir(PathBuf);
+impl TempDir {
+    /// Creates an empty directory, `name` being unique among the tests
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("cfdsim_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+impl Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Global configuration of the unit tests
+///
+/// The default binary is StarCCM+ 19.02.009, only StarCCM+ 17.06.007 is installed besides it
+/// and `zen30az000_OS_7ms` is pinned to StarCCM+ 18.04.008. None of the binaries exists.
+/// The tests using the global configuration must get it with this function rather than [crate::config]
+pub fn config() -> &'static Config {
+    let mut config = Config::default();
+    config.starccm.binary = PathBuf::from("/opt/starccm/19.02.009/star/bin/starccm+");
+    config.starccm.search.clear();
+    config.starccm.installations = BTreeMap::from([(
+        "17.06.007".to_string(),
+        PathBuf::from("/opt/starccm/17.06.007/star/bin/starccm+"),
+    )]);
+    config.starccm.pins =
+        BTreeMap::from([("zen30az000_OS_7ms".to_string(), "18.04.008".to_string())]);
+    config.license.mode = LicenseMode::None;
+    config.output.root = std::env::temp_dir().join(format!("cfdsim_output_{}", std::process::id()));
+    config.cache.dir = std::env::temp_dir().join(format!("cfdsim_cache_{}", std::process::id()));
+    config.set();
+    crate::config()
+}